@@ -11,53 +11,439 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use std::io::{BufRead, Read, Result as IoResult};
+#[cfg(feature = "encoding_rs")]
+use std::io::empty;
+#[cfg(feature = "zstd")]
+use std::io::{copy, sink};
+use std::io::{
+    BufRead, Error as IoError,
+    ErrorKind::{ConnectionReset, Other, UnexpectedEof},
+    Read, Result as IoResult,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
 
-use http::header::{HeaderMap, HeaderValue, ToStrError, TRANSFER_ENCODING};
+#[cfg(feature = "encoding_rs")]
+use encoding_rs::Encoding;
+#[cfg(feature = "sha2")]
+use http::header::HeaderName;
+use http::header::{
+    HeaderMap, HeaderValue, ToStrError, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+    TRANSFER_ENCODING,
+};
+#[cfg(feature = "zstd")]
+use ruzstd::decoding::{
+    errors::{FrameDecoderError, ReadFrameHeaderError},
+    BlockDecodingStrategy, FrameDecoder,
+};
+#[cfg(feature = "sha2")]
+use sha2::{Digest, Sha256};
 
-use super::{chunked::ChunkedReader, Error};
+#[cfg(feature = "encoding_rs")]
+use super::encoded::EncodedReader;
+use super::{
+    chunked::ChunkedReader,
+    header_value::{split_quoted, unquote},
+    stream::retry_interrupted,
+    Error, Options,
+};
+
+pub struct BodyReader {
+    reader: Box<dyn BufRead + Send>,
+    /// The charset decoding layer is kept separately so that it can be replaced.
+    #[cfg(feature = "encoding_rs")]
+    encoded: Option<EncodedReader<Box<dyn BufRead + Send>>>,
+    #[cfg(feature = "encoding_rs")]
+    capacity: usize,
+    #[cfg(feature = "encoding_rs")]
+    strict: bool,
+    len: Option<u64>,
+    lossy: bool,
+    trailers: Arc<Mutex<Option<HeaderMap>>>,
+    /// Applied on top of the charset decoding layer so that replacing it keeps inspecting.
+    inspect: Option<Box<Inspect>>,
+}
 
-pub struct BodyReader(Box<dyn BufRead + Send>);
+type Inspect = dyn FnMut(&[u8]) + Send;
 
 impl BodyReader {
     pub(crate) fn new(
+        reader: Box<dyn BufRead + Send>,
+        headers: Option<&HeaderMap>,
+        opts: &Options,
+    ) -> Result<Self, Error> {
+        Self::with_release(reader, headers, opts, None)
+    }
+
+    /// Sets `released` once a delimited body has been read completely so that the connection can be reused.
+    ///
+    /// Bodies delimited by `Content-Length` are not read beyond their end in this case.
+    pub(crate) fn with_release(
         mut reader: Box<dyn BufRead + Send>,
         headers: Option<&HeaderMap>,
+        opts: &Options,
+        released: Option<Arc<AtomicBool>>,
     ) -> Result<Self, Error> {
+        let mut len = None;
+        let mut lossy = false;
+        let trailers = Arc::default();
+        #[cfg(feature = "encoding_rs")]
+        let mut encoded = None;
+
+        if let Some(max_response_size) = opts.max_response_size {
+            reader = Box::new(LimitReader {
+                reader,
+                rem: max_response_size,
+                exceeded: || Error::BodyTooLarge,
+            });
+        }
+
+        reader = Box::new(TruncationReader {
+            reader,
+            rem: headers.and_then(content_length),
+            limit: released.is_some(),
+        });
+
         if let Some(headers) = headers {
-            reader = chunked_reader(reader, headers)?;
-            reader = compressed_reader(reader, headers)?;
-            reader = encoded_reader(reader, headers)?;
+            len = exact_length(headers);
+
+            reader = chunked_reader(reader, headers, &trailers, opts)?;
+
+            if let Some(released) = released {
+                if is_delimited(headers) {
+                    reader = Box::new(ReleaseReader { reader, released });
+                }
+            }
+            reader = compressed_reader(reader, headers, opts)?;
+
+            if let Some(charset) = declared_charset(headers) {
+                #[cfg(feature = "encoding_rs")]
+                match Encoding::for_label(charset.as_bytes()) {
+                    Some(encoding) => {
+                        encoded = Some(
+                            EncodedReader::with_capacity(reader, encoding, opts.read_buffer_size)
+                                .strict(opts.strict_decoding),
+                        );
+                        reader = Box::new(empty());
+                    }
+                    None => lossy = true,
+                }
+
+                #[cfg(not(feature = "encoding_rs"))]
+                {
+                    lossy = !charset.eq_ignore_ascii_case("utf-8");
+                }
+            }
         }
 
-        Ok(Self(reader))
+        Ok(Self {
+            reader,
+            #[cfg(feature = "encoding_rs")]
+            encoded,
+            #[cfg(feature = "encoding_rs")]
+            capacity: opts.read_buffer_size,
+            #[cfg(feature = "encoding_rs")]
+            strict: opts.strict_decoding,
+            len,
+            lossy,
+            trailers,
+            inspect: None,
+        })
+    }
+
+    /// Reads exactly `len` bytes of the decoded body, leaving the remainder for subsequent reads.
+    pub fn read_exact_vec(&mut self, len: usize) -> IoResult<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Calls the given function with each chunk of the decoded body as it is consumed, e.g. to report progress.
+    pub fn inspect<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&[u8]) + Send + 'static,
+    {
+        self.inspect = Some(match self.inspect.take() {
+            Some(mut inspect) => Box::new(move |buf: &[u8]| {
+                inspect(buf);
+                f(buf);
+            }),
+            None => Box::new(f),
+        });
+
+        self
+    }
+
+    /// The trailer fields of a chunked body which are available once it has been read completely.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.trailers.lock().unwrap().clone()
+    }
+
+    /// The exact length of the decoded body if it is reliably known from the `Content-Length` header.
+    pub fn exact_len(&self) -> Option<u64> {
+        self.len
+    }
+
+    /// Whether malformed sequences were replaced so far when decoding the declared charset.
+    #[cfg(feature = "encoding_rs")]
+    pub fn had_decoding_errors(&self) -> bool {
+        self.encoded
+            .as_ref()
+            .map_or(false, |encoded| encoded.had_errors())
+    }
+
+    /// Whether the body is declared to use a charset which is not decoded and should therefore be converted lossily.
+    pub(crate) fn is_lossy(&self) -> bool {
+        self.lossy
+    }
+
+    /// Decodes the body using the given encoding instead of the charset declared by the `Content-Type` header.
+    #[cfg(feature = "encoding_rs")]
+    pub(crate) fn decode_as(mut self, encoding: &'static Encoding) -> Self {
+        let reader = match self.encoded.take() {
+            Some(encoded) => encoded.into_inner(),
+            None => self.reader,
+        };
+
+        Self {
+            reader: Box::new(empty()),
+            encoded: Some(
+                EncodedReader::with_capacity(reader, encoding, self.capacity).strict(self.strict),
+            ),
+            capacity: self.capacity,
+            strict: self.strict,
+            len: None,
+            lossy: false,
+            trailers: self.trailers,
+            inspect: self.inspect,
+        }
+    }
+
+    fn reader(&mut self) -> &mut dyn BufRead {
+        #[cfg(feature = "encoding_rs")]
+        if let Some(encoded) = &mut self.encoded {
+            return encoded;
+        }
+
+        &mut self.reader
     }
 }
 
 impl BufRead for BodyReader {
     fn fill_buf(&mut self) -> IoResult<&[u8]> {
-        self.0.fill_buf()
+        self.reader().fill_buf()
     }
 
     fn consume(&mut self, amt: usize) {
-        self.0.consume(amt);
+        // The bytes being consumed are still buffered, so this does not perform any I/O.
+        if amt != 0 {
+            if let Some(mut inspect) = self.inspect.take() {
+                if let Ok(buf) = self.reader().fill_buf() {
+                    inspect(&buf[..amt.min(buf.len())]);
+                }
+
+                self.inspect = Some(inspect);
+            }
+        }
+
+        self.reader().consume(amt);
     }
 }
 
 impl Read for BodyReader {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
-        self.0.read(buf)
+        if self.inspect.is_none() {
+            return retry_interrupted(|| self.reader().read(buf));
+        }
+
+        retry_interrupted(|| {
+            let read = self.fill_buf()?.read(buf)?;
+            self.consume(read);
+            Ok(read)
+        })
+    }
+}
+
+/// Treats an unexpected EOF or reset of the connection, e.g. a TLS connection closed without `close_notify`,
+/// as the end of the body only if all bytes announced via `Content-Length` were received.
+///
+/// For close-delimited bodies, the error is surfaced as a truncated body cannot be told apart from a complete one.
+struct TruncationReader<R> {
+    reader: R,
+    rem: Option<u64>,
+    /// Do not read beyond the `Content-Length` as the connection will be reused.
+    limit: bool,
+}
+
+impl<R: BufRead> BufRead for TruncationReader<R> {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        let complete = self.rem == Some(0);
+
+        if complete && self.limit {
+            return Ok(&[]);
+        }
+
+        match self.reader.fill_buf() {
+            Ok(buf) if self.limit => {
+                let len = self.rem.map_or(buf.len(), |rem| {
+                    buf.len().min(rem.try_into().unwrap_or(usize::MAX))
+                });
+
+                Ok(&buf[..len])
+            }
+            Err(err) if complete && matches!(err.kind(), UnexpectedEof | ConnectionReset) => {
+                Ok(&[])
+            }
+            res => res,
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+
+        if let Some(rem) = &mut self.rem {
+            *rem = rem.saturating_sub(amt as u64);
+        }
+    }
+}
+
+impl<R: BufRead> Read for TruncationReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
     }
 }
 
+struct ReleaseReader<R> {
+    reader: R,
+    released: Arc<AtomicBool>,
+}
+
+impl<R: BufRead> BufRead for ReleaseReader<R> {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        let buf = self.reader.fill_buf()?;
+
+        if buf.is_empty() {
+            self.released.store(true, Ordering::Release);
+        }
+
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+    }
+}
+
+impl<R: BufRead> Read for ReleaseReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+/// Fails with the given error if more than the given number of bytes are read.
+struct LimitReader<R> {
+    reader: R,
+    rem: u64,
+    exceeded: fn() -> Error,
+}
+
+impl<R: BufRead> BufRead for LimitReader<R> {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        let buf = self.reader.fill_buf()?;
+
+        if !buf.is_empty() && self.rem == 0 {
+            return Err(IoError::new(Other, (self.exceeded)()));
+        }
+
+        let len = buf.len().min(self.rem.try_into().unwrap_or(usize::MAX));
+
+        Ok(&buf[..len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.rem -= amt as u64;
+    }
+}
+
+impl<R: BufRead> Read for LimitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+pub(crate) fn content_length(headers: &HeaderMap) -> Option<u64> {
+    if headers.contains_key(TRANSFER_ENCODING) {
+        return None;
+    }
+
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+/// Whether the end of the body can be determined without the connection being closed.
+fn is_delimited(headers: &HeaderMap) -> bool {
+    if content_length(headers).is_some() {
+        return true;
+    }
+
+    headers.get(TRANSFER_ENCODING).map_or(false, |encodings| {
+        split_encodings(encodings).map_or(false, |encodings| {
+            encodings.last().as_deref() == Some("chunked")
+        })
+    })
+}
+
+fn exact_length(headers: &HeaderMap) -> Option<u64> {
+    if headers.contains_key(CONTENT_ENCODING) {
+        return None;
+    }
+
+    if cfg!(feature = "encoding_rs") && declared_charset(headers).is_some() {
+        return None;
+    }
+
+    content_length(headers)
+}
+
 fn chunked_reader(
     mut reader: Box<dyn BufRead + Send>,
     headers: &HeaderMap,
+    trailers: &Arc<Mutex<Option<HeaderMap>>>,
+    opts: &Options,
 ) -> Result<Box<dyn BufRead + Send>, Error> {
     if let Some(encodings) = headers.get(TRANSFER_ENCODING) {
         for encoding in split_encodings(encodings)? {
             if encoding == "chunked" {
-                reader = Box::new(ChunkedReader::new(reader));
+                reader = Box::new(TrailerReader {
+                    reader: ChunkedReader::with_limits(reader, opts.max_body_size, opts.max_chunks),
+                    trailers: trailers.clone(),
+                    #[cfg(feature = "sha2")]
+                    hasher: if opts.verify_checksum {
+                        Some(Sha256::new())
+                    } else {
+                        None
+                    },
+                });
+
+                if opts.strict_framing {
+                    if let Some(len) = headers.get(CONTENT_LENGTH) {
+                        let expected = len.to_str().ok().and_then(|len| len.parse().ok());
+
+                        reader = Box::new(LengthCheckReader {
+                            reader,
+                            expected: expected.ok_or(Error::AmbiguousFraming)?,
+                            actual: 0,
+                        });
+                    }
+                }
             }
         }
     }
@@ -65,77 +451,262 @@ fn chunked_reader(
     Ok(reader)
 }
 
-#[cfg(feature = "flate2")]
+/// Publishes the trailer fields once the chunked body has been read completely.
+///
+/// If enabled, the SHA-256 checksum of the body is verified against the `X-Checksum-Sha256` trailer field.
+struct TrailerReader<R> {
+    reader: ChunkedReader<R>,
+    trailers: Arc<Mutex<Option<HeaderMap>>>,
+    #[cfg(feature = "sha2")]
+    hasher: Option<Sha256>,
+}
+
+impl<R: BufRead> TrailerReader<R> {
+    fn finish(&mut self) -> IoResult<()> {
+        let trailers = self.reader.trailers();
+
+        #[cfg(feature = "sha2")]
+        if let Some(hasher) = self.hasher.take() {
+            if let Some(expected) = trailers.get(X_CHECKSUM_SHA256) {
+                let actual = hasher
+                    .finalize()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+
+                if !expected.as_bytes().eq_ignore_ascii_case(actual.as_bytes()) {
+                    return Err(IoError::new(Other, Error::ChecksumMismatch));
+                }
+            }
+        }
+
+        *self.trailers.lock().unwrap() = Some(trailers.clone());
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sha2")]
+const X_CHECKSUM_SHA256: HeaderName = HeaderName::from_static("x-checksum-sha256");
+
+impl<R: BufRead> BufRead for TrailerReader<R> {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        if self.reader.fill_buf()?.is_empty() && self.trailers.lock().unwrap().is_none() {
+            self.finish()?;
+        }
+
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The bytes being consumed are still buffered, so this does not perform any I/O.
+        #[cfg(feature = "sha2")]
+        if let Some(hasher) = &mut self.hasher {
+            if amt != 0 {
+                if let Ok(buf) = self.reader.fill_buf() {
+                    hasher.update(&buf[..amt.min(buf.len())]);
+                }
+            }
+        }
+
+        self.reader.consume(amt);
+    }
+}
+
+impl<R: BufRead> Read for TrailerReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+/// Fails with [`Error::AmbiguousFraming`] at the end of a chunked body if its decoded length
+/// does not match the `Content-Length` header which was ignored in favour of the chunked framing.
+struct LengthCheckReader<R> {
+    reader: R,
+    expected: u64,
+    actual: u64,
+}
+
+impl<R: BufRead> BufRead for LengthCheckReader<R> {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        let buf = self.reader.fill_buf()?;
+
+        if buf.is_empty() && self.actual != self.expected {
+            return Err(IoError::new(Other, Error::AmbiguousFraming));
+        }
+
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.actual += amt as u64;
+    }
+}
+
+impl<R: BufRead> Read for LengthCheckReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+#[cfg(any(feature = "brotli", feature = "flate2", feature = "zstd"))]
 fn compressed_reader(
     mut reader: Box<dyn BufRead + Send>,
     headers: &HeaderMap,
+    opts: &Options,
 ) -> Result<Box<dyn BufRead + Send>, Error> {
     use std::io::BufReader;
 
+    #[cfg(feature = "brotli")]
+    use brotli_decompressor::Decompressor;
+    #[cfg(feature = "flate2")]
     use flate2::bufread::{GzDecoder, ZlibDecoder};
-    use http::header::CONTENT_ENCODING;
 
-    fn deflate_reader(reader: Box<dyn BufRead + Send>) -> Box<dyn BufRead + Send> {
-        Box::new(BufReader::new(ZlibDecoder::new(reader)))
-    }
+    #[cfg(feature = "brotli")]
+    let brotli_reader = |reader| -> Box<dyn BufRead + Send> {
+        Box::new(BufReader::with_capacity(
+            opts.read_buffer_size,
+            Decompressor::new(reader, opts.read_buffer_size),
+        ))
+    };
 
-    fn gzip_reader(reader: Box<dyn BufRead + Send>) -> Box<dyn BufRead + Send> {
-        Box::new(BufReader::new(GzDecoder::new(reader)))
-    }
+    #[cfg(feature = "flate2")]
+    let deflate_reader = |reader| -> Box<dyn BufRead + Send> {
+        Box::new(BufReader::with_capacity(
+            opts.read_buffer_size,
+            ZlibDecoder::new(reader),
+        ))
+    };
+
+    #[cfg(feature = "flate2")]
+    let gzip_reader = |reader| -> Box<dyn BufRead + Send> {
+        Box::new(BufReader::with_capacity(
+            opts.read_buffer_size,
+            GzDecoder::new(reader),
+        ))
+    };
+
+    #[cfg(feature = "zstd")]
+    let zstd_reader = |reader| -> Box<dyn BufRead + Send> {
+        Box::new(BufReader::with_capacity(
+            opts.read_buffer_size,
+            ZstdReader {
+                reader,
+                decoder: FrameDecoder::new(),
+                started: false,
+            },
+        ))
+    };
 
     if let Some(encodings) = headers.get(CONTENT_ENCODING) {
-        for encoding in split_encodings(encodings)? {
+        // The codings are listed in the order they were applied, so the last one must be decoded first.
+        let encodings = split_encodings(encodings)?.collect::<Vec<_>>();
+        let mut decoded = false;
+
+        for encoding in encodings.iter().rev() {
             reader = match encoding.as_str() {
-                "deflate" => deflate_reader(reader),
-                "gzip" => gzip_reader(reader),
-                _ => reader,
+                #[cfg(feature = "brotli")]
+                "br" => brotli_reader(reader),
+                #[cfg(feature = "flate2")]
+                "deflate" | "x-deflate" => deflate_reader(reader),
+                #[cfg(feature = "flate2")]
+                "gzip" | "x-gzip" => gzip_reader(reader),
+                #[cfg(feature = "zstd")]
+                "zstd" => zstd_reader(reader),
+                _ => continue,
             };
+
+            decoded = true;
+        }
+
+        if let Some(max_decompressed_size) = opts.max_decompressed_size.filter(|_| decoded) {
+            reader = Box::new(LimitReader {
+                reader,
+                rem: max_decompressed_size,
+                exceeded: || Error::DecompressedBodyTooLarge,
+            });
         }
     }
 
     Ok(reader)
 }
 
-#[cfg(not(feature = "flate2"))]
-#[allow(clippy::unnecessary_wraps)]
-fn compressed_reader(
-    reader: Box<dyn BufRead + Send>,
-    _headers: &HeaderMap,
-) -> Result<Box<dyn BufRead + Send>, Error> {
-    Ok(reader)
+/// Decodes all concatenated Zstandard frames instead of stopping after the first one.
+#[cfg(feature = "zstd")]
+struct ZstdReader<R> {
+    reader: R,
+    decoder: FrameDecoder,
+    started: bool,
 }
 
-#[cfg(feature = "encoding_rs")]
-fn encoded_reader(
-    mut reader: Box<dyn BufRead + Send>,
-    headers: &HeaderMap,
-) -> Result<Box<dyn BufRead + Send>, Error> {
-    use encoding_rs::Encoding;
-    use http::header::CONTENT_TYPE;
+#[cfg(feature = "zstd")]
+impl<R: BufRead> Read for ZstdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        loop {
+            if !self.started || (self.decoder.is_finished() && self.decoder.can_collect() == 0) {
+                if self.reader.fill_buf()?.is_empty() {
+                    return Ok(0);
+                }
 
-    use super::encoded::EncodedReader;
+                match self.decoder.reset(&mut self.reader) {
+                    Ok(()) => self.started = true,
+                    Err(FrameDecoderError::ReadFrameHeaderError(
+                        ReadFrameHeaderError::SkipFrame { length, .. },
+                    )) => {
+                        copy(&mut (&mut self.reader).take(length.into()), &mut sink())?;
+                        continue;
+                    }
+                    Err(err) => return Err(IoError::new(Other, err)),
+                }
+            }
+
+            while self.decoder.can_collect() < buf.len() && !self.decoder.is_finished() {
+                let needed = buf.len() - self.decoder.can_collect();
+
+                self.decoder
+                    .decode_blocks(&mut self.reader, BlockDecodingStrategy::UptoBytes(needed))
+                    .map_err(|err| IoError::new(Other, err))?;
+            }
+
+            let read = self.decoder.read(buf)?;
 
-    if let Some(type_) = headers.get(CONTENT_TYPE) {
-        #[allow(clippy::manual_split_once)]
-        if let Some(charset) = type_.to_str()?.splitn(2, "charset=").nth(1) {
-            if let Some(encoding) = Encoding::for_label(charset.as_bytes()) {
-                reader = Box::new(EncodedReader::new(reader, encoding));
+            if read != 0 || buf.is_empty() {
+                return Ok(read);
             }
         }
     }
-
-    Ok(reader)
 }
 
-#[cfg(not(feature = "encoding_rs"))]
+#[cfg(not(any(feature = "brotli", feature = "flate2", feature = "zstd")))]
 #[allow(clippy::unnecessary_wraps)]
-fn encoded_reader(
+fn compressed_reader(
     reader: Box<dyn BufRead + Send>,
     _headers: &HeaderMap,
+    _opts: &Options,
 ) -> Result<Box<dyn BufRead + Send>, Error> {
     Ok(reader)
 }
 
+/// A `Content-Type` header which is not visible ASCII is treated as not declaring a charset.
+fn declared_charset(headers: &HeaderMap) -> Option<&str> {
+    let type_ = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+
+    split_quoted(type_, b';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(unquote(value.trim()))
+        } else {
+            None
+        }
+    })
+}
+
 fn split_encodings(
     encodings: &HeaderValue,
 ) -> Result<impl Iterator<Item = String> + '_, ToStrError> {
@@ -146,3 +717,365 @@ fn split_encodings(
             .map(str::to_ascii_lowercase)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    struct Truncated(&'static [u8], ErrorKind);
+
+    impl Read for Truncated {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            match self.0.read(buf)? {
+                0 if !buf.is_empty() => Err(self.1.into()),
+                read => Ok(read),
+            }
+        }
+    }
+
+    fn read_truncated(headers: &HeaderMap) -> IoResult<Vec<u8>> {
+        read_until(headers, UnexpectedEof)
+    }
+
+    fn read_until(headers: &HeaderMap, kind: ErrorKind) -> IoResult<Vec<u8>> {
+        let reader = std::io::BufReader::new(Truncated(b"foobar", kind));
+
+        let mut reader = BodyReader::new(Box::new(reader), Some(headers), &Options::default())
+            .map_err(|err| IoError::new(Other, err))?;
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    #[test]
+    fn unexpected_eof_after_content_length() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("6"));
+
+        assert_eq!(b"foobar", &read_truncated(&headers).unwrap()[..]);
+    }
+
+    #[test]
+    fn unexpected_eof_before_content_length() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("10"));
+
+        let err = read_truncated(&headers).unwrap_err();
+        assert_eq!(UnexpectedEof, err.kind());
+    }
+
+    #[test]
+    fn unexpected_eof_without_content_length() {
+        let headers = HeaderMap::new();
+
+        let err = read_truncated(&headers).unwrap_err();
+        assert_eq!(UnexpectedEof, err.kind());
+    }
+
+    #[test]
+    fn connection_reset_after_content_length() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("6"));
+
+        assert_eq!(
+            b"foobar",
+            &read_until(&headers, ConnectionReset).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn connection_reset_before_content_length() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("10"));
+
+        let err = read_until(&headers, ConnectionReset).unwrap_err();
+        assert_eq!(ConnectionReset, err.kind());
+    }
+
+    #[test]
+    fn connection_reset_without_content_length() {
+        let headers = HeaderMap::new();
+
+        let err = read_until(&headers, ConnectionReset).unwrap_err();
+        assert_eq!(ConnectionReset, err.kind());
+    }
+
+    #[test]
+    fn unexpected_eof_within_chunks() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+
+        read_truncated(&headers).unwrap_err();
+    }
+
+    #[test]
+    fn read_exact_vec_from_chunks() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+
+        let body = &b"2\r\nfo\r\n4\r\nobar\r\n0\r\n\r\n"[..];
+        let mut reader =
+            BodyReader::new(Box::new(body), Some(&headers), &Options::default()).unwrap();
+
+        assert_eq!(b"foo", &reader.read_exact_vec(3).unwrap()[..]);
+        assert_eq!(b"bar", &reader.read_exact_vec(3).unwrap()[..]);
+
+        let err = reader.read_exact_vec(1).unwrap_err();
+        assert_eq!(UnexpectedEof, err.kind());
+    }
+
+    fn read_strictly_framed(content_length: &'static str) -> IoResult<Vec<u8>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static(content_length));
+
+        let opts = Options {
+            strict_framing: true,
+            ..Default::default()
+        };
+
+        let body = &b"3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n"[..];
+        let mut reader = BodyReader::new(Box::new(body), Some(&headers), &opts).unwrap();
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    #[test]
+    fn chunked_length_matches_content_length() {
+        assert_eq!(b"foobar", &read_strictly_framed("6").unwrap()[..]);
+    }
+
+    #[test]
+    fn chunked_length_differs_from_content_length() {
+        let err = read_strictly_framed("10").unwrap_err();
+        assert_eq!(Other, err.kind());
+        assert!(matches!(
+            err.into_inner().unwrap().downcast::<Error>().as_deref(),
+            Ok(Error::AmbiguousFraming)
+        ));
+    }
+
+    #[cfg(any(feature = "brotli", feature = "flate2", feature = "zstd"))]
+    fn read_compressed(encoding: &'static str, body: Vec<u8>) -> String {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+
+        let mut reader = BodyReader::new(
+            Box::new(std::io::Cursor::new(body)),
+            Some(&headers),
+            &Options::default(),
+        )
+        .unwrap();
+
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+        buf
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn decode_legacy_gzip() {
+        use std::io::Write;
+
+        use flate2::write::GzEncoder;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(b"foobar").unwrap();
+        let body = encoder.finish().unwrap();
+
+        assert_eq!("foobar", read_compressed("x-gzip", body));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn decode_legacy_deflate() {
+        use std::io::Write;
+
+        use flate2::write::ZlibEncoder;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(b"foobar").unwrap();
+        let body = encoder.finish().unwrap();
+
+        assert_eq!("foobar", read_compressed("X-Deflate", body));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn limit_decompressed_size() {
+        use std::io::Write;
+
+        use flate2::write::GzEncoder;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(&[0; 1024 * 1024]).unwrap();
+        let body = encoder.finish().unwrap();
+        assert!(body.len() < 4096);
+
+        let read_limited = |max_decompressed_size| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+
+            let opts = Options {
+                max_decompressed_size: Some(max_decompressed_size),
+                ..Default::default()
+            };
+
+            let mut reader = BodyReader::new(
+                Box::new(std::io::Cursor::new(body.clone())),
+                Some(&headers),
+                &opts,
+            )
+            .unwrap();
+
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).map(|_| buf.len())
+        };
+
+        assert_eq!(1024 * 1024, read_limited(1024 * 1024).unwrap());
+
+        let err = read_limited(4096).unwrap_err();
+        assert!(matches!(
+            err.into_inner().unwrap().downcast::<Error>().as_deref(),
+            Ok(Error::DecompressedBodyTooLarge)
+        ));
+    }
+
+    #[cfg(feature = "brotli")]
+    const BROTLI_FOOBAR: &[u8] = &[0x8b, 0x02, 0x80, b'f', b'o', b'o', b'b', b'a', b'r', 0x03];
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn decode_brotli() {
+        assert_eq!("foobar", read_compressed("br", BROTLI_FOOBAR.to_vec()));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn decode_concatenated_zstd_frames() {
+        use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+        let mut body = compress_to_vec(&b"foo"[..], CompressionLevel::Fastest);
+        // A skippable frame between the two data frames
+        body.extend_from_slice(&[0x50, 0x2a, 0x4d, 0x18, 3, 0, 0, 0, 1, 2, 3]);
+        body.extend(compress_to_vec(&b"bar"[..], CompressionLevel::Fastest));
+
+        assert_eq!("foobar", read_compressed("zstd", body));
+    }
+
+    #[cfg(all(feature = "brotli", feature = "flate2"))]
+    #[test]
+    fn decode_nested_encodings_in_reverse() {
+        use std::io::Write;
+
+        use flate2::write::GzEncoder;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(BROTLI_FOOBAR).unwrap();
+        let body = encoder.finish().unwrap();
+
+        assert_eq!("foobar", read_compressed("br, gzip", body));
+    }
+
+    #[test]
+    fn inspect_sees_all_bytes() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+
+        let reader: &'static [u8] = b"3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n";
+        let reader =
+            BodyReader::new(Box::new(reader), Some(&headers), &Options::default()).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut reader = reader.inspect(move |buf| tx.send(buf.to_vec()).unwrap());
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        drop(reader);
+
+        assert_eq!(buf, b"foobar");
+        assert_eq!(rx.iter().collect::<Vec<_>>().concat(), b"foobar");
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn inspect_keeps_decoding_state() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+
+        let reader: &'static [u8] = b"foo\xffbar";
+        let reader =
+            BodyReader::new(Box::new(reader), Some(&headers), &Options::default()).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut reader = reader
+            .inspect(move |buf| tx.send(buf.to_vec()).unwrap())
+            .decode_as(encoding_rs::UTF_8);
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert!(reader.had_decoding_errors());
+        drop(reader);
+
+        assert_eq!(buf, "foo\u{fffd}bar".as_bytes());
+        assert_eq!(rx.iter().collect::<Vec<_>>().concat(), buf);
+    }
+
+    fn charset_of(type_: &'static str) -> Option<String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(type_));
+
+        declared_charset(&headers).map(str::to_owned)
+    }
+
+    #[test]
+    fn charset_followed_by_other_parameters() {
+        assert_eq!(
+            charset_of("text/html; charset=utf-8; boundary=x").as_deref(),
+            Some("utf-8")
+        );
+        assert_eq!(
+            charset_of("text/html;boundary=x;Charset=latin1").as_deref(),
+            Some("latin1")
+        );
+        assert_eq!(charset_of("text/html; boundary=x"), None);
+        assert_eq!(charset_of("text/html"), None);
+    }
+
+    #[test]
+    fn reads_body_with_opaque_content_type() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_bytes(b"text/plain; name=\xe4").unwrap(),
+        );
+
+        let reader: &'static [u8] = b"foobar";
+        let mut reader =
+            BodyReader::new(Box::new(reader), Some(&headers), &Options::default()).unwrap();
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"foobar");
+    }
+
+    #[test]
+    fn quoted_charset() {
+        assert_eq!(
+            charset_of(r#"text/html; charset="UTF-8"; boundary="a;b""#).as_deref(),
+            Some("UTF-8")
+        );
+        assert_eq!(
+            charset_of(r#"multipart/mixed; boundary="x;charset=y"; charset=latin1"#).as_deref(),
+            Some("latin1")
+        );
+    }
+}
@@ -11,8 +11,20 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use std::collections::hash_map::RandomState;
 use std::convert::TryInto;
-use std::io::{copy, Read, Result as IoResult, Seek, SeekFrom, Write};
+use std::ffi::OsStr;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{
+    copy, Error as IoError, ErrorKind::Other, Read, Result as IoResult, Seek, SeekFrom, Write,
+};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use http::header::{HeaderMap, HeaderName};
+
+use super::{chunked::ChunkedWriter, Error};
 
 #[derive(Debug, Clone, Copy)]
 pub enum BodyKind {
@@ -24,6 +36,42 @@ pub enum BodyKind {
 pub trait BodyWriter {
     fn kind(&mut self) -> IoResult<BodyKind>;
     fn write<W: Write>(&mut self, writer: W) -> IoResult<()>;
+
+    /// Whether the body can be written again, e.g. to retry a request after a connection failure.
+    fn replayable(&self) -> bool {
+        false
+    }
+
+    /// Names of the trailer fields sent after a chunked body, announced using the `Trailer` header.
+    fn trailer_names(&mut self) -> Vec<HeaderName> {
+        Vec::new()
+    }
+
+    /// Trailer fields sent after a chunked body, called after it has been written.
+    fn trailers(&mut self) -> HeaderMap {
+        HeaderMap::new()
+    }
+}
+
+/// Writes the body using the same framing as when sending a request,
+/// i.e. bodies of kind [`BodyKind::Chunked`] are chunk-encoded.
+pub fn write_body<B: BodyWriter, W: Write>(body: &mut B, writer: W) -> IoResult<()> {
+    let chunked = matches!(body.kind()?, BodyKind::Chunked);
+    write_framed(body, writer, chunked)
+}
+
+pub(crate) fn write_framed<B: BodyWriter, W: Write>(
+    body: &mut B,
+    mut writer: W,
+    chunked: bool,
+) -> IoResult<()> {
+    if chunked {
+        let mut writer = ChunkedWriter(&mut writer);
+        body.write(&mut writer)?;
+        writer.close_with_trailers(&body.trailers())
+    } else {
+        body.write(writer)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -37,6 +85,28 @@ impl BodyWriter for EmptyBody {
     fn write<W: Write>(&mut self, _writer: W) -> IoResult<()> {
         Ok(())
     }
+
+    fn replayable(&self) -> bool {
+        true
+    }
+}
+
+/// Sends no data using chunked encoding, i.e. only the terminating chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct EmptyChunkedBody;
+
+impl BodyWriter for EmptyChunkedBody {
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        Ok(BodyKind::Chunked)
+    }
+
+    fn write<W: Write>(&mut self, _writer: W) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn replayable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +122,29 @@ impl<B: AsRef<[u8]>> BodyWriter for MemBody<B> {
         writer.write_all(self.0.as_ref())?;
         Ok(())
     }
+
+    fn replayable(&self) -> bool {
+        true
+    }
+}
+
+/// Sends the wrapped bytes using chunked encoding even though their length is known, e.g. for testing.
+#[derive(Debug, Clone)]
+pub struct ChunkedMemBody<B>(pub B);
+
+impl<B: AsRef<[u8]>> BodyWriter for ChunkedMemBody<B> {
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        Ok(BodyKind::Chunked)
+    }
+
+    fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+        writer.write_all(self.0.as_ref())?;
+        Ok(())
+    }
+
+    fn replayable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -68,13 +161,348 @@ impl<B: Seek + Read> BodyWriter for IoBody<B> {
         copy(&mut self.0, &mut writer)?;
         Ok(())
     }
+
+    fn replayable(&self) -> bool {
+        true
+    }
+}
+
+/// Sends a seekable reader like [`IoBody`], but falls back to chunked encoding
+/// if seeking to its end fails or yields a length of zero, e.g. for pipes.
+#[derive(Debug, Clone)]
+pub struct ProbedIoBody<B> {
+    body: B,
+    seekable: bool,
+}
+
+impl<B> ProbedIoBody<B> {
+    pub fn new(body: B) -> Self {
+        Self {
+            body,
+            seekable: true,
+        }
+    }
+}
+
+impl<B: Seek + Read> BodyWriter for ProbedIoBody<B> {
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        match self.body.seek(SeekFrom::End(0)) {
+            Ok(0) => Ok(BodyKind::Chunked),
+            Ok(len) => Ok(BodyKind::KnownLength(len)),
+            Err(_) => {
+                self.seekable = false;
+                Ok(BodyKind::Chunked)
+            }
+        }
+    }
+
+    fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+        if self.seekable {
+            self.body.seek(SeekFrom::Start(0))?;
+        }
+        copy(&mut self.body, &mut writer)?;
+        Ok(())
+    }
+
+    fn replayable(&self) -> bool {
+        self.seekable
+    }
+}
+
+/// Sends a file whose length is determined once when the body is created.
+///
+/// Writing fails with [`Error::ContentLengthMismatch`] if the file changed its size since then.
+#[derive(Debug)]
+pub struct FileBody {
+    file: File,
+    len: u64,
+}
+
+impl FileBody {
+    pub fn new(file: File) -> IoResult<Self> {
+        let len = file.metadata()?.len();
+
+        Ok(Self { file, len })
+    }
+}
+
+impl BodyWriter for FileBody {
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        Ok(BodyKind::KnownLength(self.len))
+    }
+
+    fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+
+        // Sending more than the announced length would corrupt the framing of the next request.
+        let written = copy(&mut (&mut self.file).take(self.len), &mut writer)?;
+
+        if written != self.len || self.file.read(&mut [0])? != 0 {
+            return Err(IoError::new(Other, Error::ContentLengthMismatch));
+        }
+
+        Ok(())
+    }
+
+    fn replayable(&self) -> bool {
+        true
+    }
+}
+
+/// Streams a chunked body produced by calling the wrapped closure repeatedly until it returns [`ControlFlow::Break`].
+///
+/// The writer is flushed after each call, e.g. to send one line of a line-based protocol at a time.
+pub struct StreamingBody<F>(pub F);
+
+impl<F> BodyWriter for StreamingBody<F>
+where
+    F: FnMut(&mut dyn Write) -> IoResult<ControlFlow<()>>,
+{
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        Ok(BodyKind::Chunked)
+    }
+
+    fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+        loop {
+            let flow = (self.0)(&mut writer)?;
+            writer.flush()?;
+
+            if flow.is_break() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Sends named text fields and file parts as `multipart/form-data`.
+///
+/// The body has a known length if all file parts are seekable and is sent using chunked encoding otherwise.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::Request, MultipartBody, RequestBuilderExt, RequestExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let form = MultipartBody::new()
+///     .text("description", "holiday")
+///     .seekable_file("photo", "beach.jpg", "image/jpeg", std::fs::File::open("beach.jpg")?);
+///
+/// let resp = Request::post("http://httpbin.org/post").multipart(form)?.send()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultipartBody {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+struct Part {
+    head: String,
+    content: Content,
+}
+
+enum Content {
+    Text(String),
+    Stream(Box<dyn Read + Send>),
+    Seekable(Box<dyn ReadSeek + Send>),
+}
+
+trait ReadSeek: Read + Seek {}
+
+impl<R> ReadSeek for R where R: Read + Seek {}
+
+impl MultipartBody {
+    pub fn new() -> Self {
+        let hasher = RandomState::new().build_hasher();
+
+        Self {
+            boundary: format!("zeptohttpc-{:016x}", hasher.finish()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// The boundary separating the parts which is part of the `Content-Type` header.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// The value of the `Content-Type` header including the boundary.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Adds a text field.
+    pub fn text(mut self, name: &str, value: impl Into<String>) -> Self {
+        let head = self.head(name, None);
+
+        self.parts.push(Part {
+            head,
+            content: Content::Text(value.into()),
+        });
+
+        self
+    }
+
+    /// Adds a file part whose length is not known which forces chunked encoding.
+    pub fn file<R>(mut self, name: &str, filename: &str, content_type: &str, reader: R) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        let head = self.head(name, Some((filename, content_type)));
+
+        self.parts.push(Part {
+            head,
+            content: Content::Stream(Box::new(reader)),
+        });
+
+        self
+    }
+
+    /// Adds a file part whose length is determined by seeking to its end.
+    pub fn seekable_file<R>(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: &str,
+        reader: R,
+    ) -> Self
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let head = self.head(name, Some((filename, content_type)));
+
+        self.parts.push(Part {
+            head,
+            content: Content::Seekable(Box::new(reader)),
+        });
+
+        self
+    }
+
+    fn head(&self, name: &str, file: Option<(&str, &str)>) -> String {
+        let mut head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape_quoted(name)
+        );
+
+        if let Some((filename, content_type)) = file {
+            head.push_str(&format!(
+                "; filename=\"{}\"\r\nContent-Type: {}",
+                escape_quoted(filename),
+                content_type
+            ));
+        }
+
+        head.push_str("\r\n\r\n");
+        head
+    }
+
+    fn trailer(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+impl Default for MultipartBody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BodyWriter for MultipartBody {
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        let mut len = self.trailer().len() as u64;
+
+        for part in &mut self.parts {
+            len += part.head.len() as u64 + 2;
+
+            len += match &mut part.content {
+                Content::Text(text) => text.len() as u64,
+                Content::Stream(_) => return Ok(BodyKind::Chunked),
+                Content::Seekable(reader) => reader.seek(SeekFrom::End(0))?,
+            };
+        }
+
+        Ok(BodyKind::KnownLength(len))
+    }
+
+    fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+        let trailer = self.trailer();
+
+        for part in &mut self.parts {
+            writer.write_all(part.head.as_bytes())?;
+
+            match &mut part.content {
+                Content::Text(text) => writer.write_all(text.as_bytes())?,
+                Content::Stream(reader) => {
+                    copy(reader, &mut writer)?;
+                }
+                Content::Seekable(reader) => {
+                    reader.seek(SeekFrom::Start(0))?;
+                    copy(reader, &mut writer)?;
+                }
+            }
+
+            writer.write_all(b"\r\n")?;
+        }
+
+        writer.write_all(trailer.as_bytes())?;
+        Ok(())
+    }
+
+    fn replayable(&self) -> bool {
+        !self
+            .parts
+            .iter()
+            .any(|part| matches!(part.content, Content::Stream(_)))
+    }
+}
+
+/// Escapes quotes and line breaks in names of fields and files as browsers do.
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Infers the media type of a file from its extension, defaulting to `application/octet-stream`.
+pub(crate) fn content_type_of(path: &Path) -> &'static str {
+    let ext = match path.extension().and_then(OsStr::to_str) {
+        Some(ext) => ext.to_ascii_lowercase(),
+        None => return "application/octet-stream",
+    };
+
+    match ext.as_str() {
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/vnd.microsoft.icon",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
 }
 
 #[cfg(feature = "flate2")]
 pub mod compressed_body {
     use super::*;
 
-    use flate2::write::GzEncoder;
+    use flate2::{write::GzEncoder, Compression};
 
     #[derive(Debug, Clone)]
     pub struct CompressedBody<B>(pub B);
@@ -85,10 +513,109 @@ pub mod compressed_body {
         }
 
         fn write<W: Write>(&mut self, writer: W) -> IoResult<()> {
-            let mut writer = GzEncoder::new(writer, Default::default());
-            self.0.write(&mut writer)?;
-            writer.finish()?;
-            Ok(())
+            write_compressed(&mut self.0, writer, Compression::default())
+        }
+
+        fn trailer_names(&mut self) -> Vec<HeaderName> {
+            self.0.trailer_names()
+        }
+
+        fn trailers(&mut self) -> HeaderMap {
+            self.0.trailers()
+        }
+
+        fn replayable(&self) -> bool {
+            self.0.replayable()
+        }
+    }
+
+    /// Compresses the wrapped body like [`CompressedBody`], but using the given level.
+    #[derive(Debug, Clone)]
+    pub struct CompressedBodyWithLevel<B> {
+        body: B,
+        level: Compression,
+    }
+
+    impl<B> CompressedBodyWithLevel<B> {
+        pub fn new(body: B, level: Compression) -> Self {
+            Self { body, level }
+        }
+    }
+
+    impl<B: BodyWriter> BodyWriter for CompressedBodyWithLevel<B> {
+        fn kind(&mut self) -> IoResult<BodyKind> {
+            Ok(BodyKind::Chunked)
+        }
+
+        fn write<W: Write>(&mut self, writer: W) -> IoResult<()> {
+            write_compressed(&mut self.body, writer, self.level)
+        }
+
+        fn trailer_names(&mut self) -> Vec<HeaderName> {
+            self.body.trailer_names()
+        }
+
+        fn trailers(&mut self) -> HeaderMap {
+            self.body.trailers()
+        }
+
+        fn replayable(&self) -> bool {
+            self.body.replayable()
+        }
+    }
+
+    fn write_compressed<B: BodyWriter, W: Write>(
+        body: &mut B,
+        writer: W,
+        level: Compression,
+    ) -> IoResult<()> {
+        let mut writer = GzEncoder::new(writer, level);
+        body.write(&mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Either compresses the wrapped body or passes it through unchanged.
+    #[derive(Debug, Clone)]
+    pub enum MaybeCompressedBody<B> {
+        Compressed(CompressedBody<B>),
+        Plain(B),
+    }
+
+    impl<B: BodyWriter> BodyWriter for MaybeCompressedBody<B> {
+        fn kind(&mut self) -> IoResult<BodyKind> {
+            match self {
+                Self::Compressed(body) => body.kind(),
+                Self::Plain(body) => body.kind(),
+            }
+        }
+
+        fn write<W: Write>(&mut self, writer: W) -> IoResult<()> {
+            match self {
+                Self::Compressed(body) => body.write(writer),
+                Self::Plain(body) => body.write(writer),
+            }
+        }
+
+        fn trailer_names(&mut self) -> Vec<HeaderName> {
+            match self {
+                Self::Compressed(body) => body.trailer_names(),
+                Self::Plain(body) => body.trailer_names(),
+            }
+        }
+
+        fn trailers(&mut self) -> HeaderMap {
+            match self {
+                Self::Compressed(body) => body.trailers(),
+                Self::Plain(body) => body.trailers(),
+            }
+        }
+
+        fn replayable(&self) -> bool {
+            match self {
+                Self::Compressed(body) => body.replayable(),
+                Self::Plain(body) => body.replayable(),
+            }
         }
     }
 }
@@ -116,5 +643,388 @@ pub mod json_body {
             writer.flush()?;
             Ok(())
         }
+
+        fn replayable(&self) -> bool {
+            true
+        }
+    }
+}
+
+#[cfg(feature = "sha2")]
+pub mod payload_hash {
+    use super::*;
+
+    use sha2::{Digest, Sha256};
+
+    /// Computes the hex-encoded SHA-256 digest of the payload, e.g. for signing requests using AWS Signature Version 4.
+    ///
+    /// Implemented for bodies which can be written repeatedly so that the digest is computed without buffering.
+    /// Other bodies can be buffered using [`buffer_payload`]. Streaming bodies sent with an `UNSIGNED-PAYLOAD`
+    /// do not need to be hashed at all and keep using chunked encoding.
+    pub trait PayloadHash: BodyWriter {
+        fn payload_hash(&mut self) -> IoResult<String> {
+            let mut writer = HashWriter(Sha256::new());
+            self.write(&mut writer)?;
+            Ok(writer.finish())
+        }
+    }
+
+    impl PayloadHash for EmptyBody {}
+
+    impl<B: AsRef<[u8]>> PayloadHash for MemBody<B> {}
+
+    impl<B: AsRef<[u8]>> PayloadHash for ChunkedMemBody<B> {}
+
+    impl<B: Seek + Read> PayloadHash for IoBody<B> {}
+
+    /// Buffers the payload of an arbitrary body in memory and computes its digest like [`PayloadHash`],
+    /// returning a body which replays the hashed payload.
+    pub fn buffer_payload<B: BodyWriter>(mut body: B) -> IoResult<(String, MemBody<Vec<u8>>)> {
+        let mut buf = Vec::new();
+        body.write(&mut buf)?;
+
+        let mut body = MemBody(buf);
+        let hash = body.payload_hash()?;
+
+        Ok((hash, body))
+    }
+
+    struct HashWriter(Sha256);
+
+    impl HashWriter {
+        fn finish(self) -> String {
+            self.0
+                .finalize()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()
+        }
+    }
+
+    impl Write for HashWriter {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            self.0.update(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_body_fails_if_size_changed() {
+        use std::fs::{remove_file, write, OpenOptions};
+
+        let path =
+            std::env::temp_dir().join(format!("zeptohttpc-file-body-{}", std::process::id()));
+
+        let write_file_body = |contents: &str| {
+            write(&path, "foobar").unwrap();
+            let mut body = FileBody::new(File::open(&path).unwrap()).unwrap();
+
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_len(0).unwrap();
+            file.write_all(contents.as_bytes()).unwrap();
+
+            let mut buf = Vec::new();
+            write_body(&mut body, &mut buf).map(|()| buf)
+        };
+
+        assert_eq!(write_file_body("bazqux").unwrap(), b"bazqux");
+
+        let err = write_file_body("foobarbaz").unwrap_err();
+        assert!(matches!(
+            err.into_inner().unwrap().downcast::<Error>().as_deref(),
+            Ok(Error::ContentLengthMismatch)
+        ));
+
+        write_file_body("foo").unwrap_err();
+
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn write_mem_body() {
+        let mut buf = Vec::new();
+        write_body(&mut MemBody("foobar"), &mut buf).unwrap();
+        assert_eq!(b"foobar", &buf[..]);
+    }
+
+    #[test]
+    fn write_chunked_mem_body() {
+        let mut buf = Vec::new();
+        write_body(&mut ChunkedMemBody("foobar"), &mut buf).unwrap();
+        assert_eq!(b"6\r\nfoobar\r\n0\r\n\r\n", &buf[..]);
+    }
+
+    #[test]
+    fn write_empty_chunked_body() {
+        let mut buf = Vec::new();
+        write_body(&mut EmptyChunkedBody, &mut buf).unwrap();
+        assert_eq!(b"0\r\n\r\n", &buf[..]);
+    }
+
+    #[test]
+    fn write_probed_io_body() {
+        use std::io::{Cursor, ErrorKind};
+
+        struct Pipe<R> {
+            reader: R,
+            seek: fn(SeekFrom) -> IoResult<u64>,
+        }
+
+        impl<R: Read> Read for Pipe<R> {
+            fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+                self.reader.read(buf)
+            }
+        }
+
+        impl<R> Seek for Pipe<R> {
+            fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+                (self.seek)(pos)
+            }
+        }
+
+        let mut buf = Vec::new();
+        write_body(&mut ProbedIoBody::new(Cursor::new("foobar")), &mut buf).unwrap();
+        assert_eq!(b"foobar", &buf[..]);
+
+        let mut body = ProbedIoBody::new(Pipe {
+            reader: &b"foobar"[..],
+            seek: |_pos| Ok(0),
+        });
+        let mut buf = Vec::new();
+        write_body(&mut body, &mut buf).unwrap();
+        assert_eq!(b"6\r\nfoobar\r\n0\r\n\r\n", &buf[..]);
+
+        let mut body = ProbedIoBody::new(Pipe {
+            reader: &b"foobar"[..],
+            seek: |_pos| Err(ErrorKind::Unsupported.into()),
+        });
+        let mut buf = Vec::new();
+        write_body(&mut body, &mut buf).unwrap();
+        assert_eq!(b"6\r\nfoobar\r\n0\r\n\r\n", &buf[..]);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn write_compressed_body() {
+        use flate2::read::GzDecoder;
+
+        use super::super::chunked::ChunkedReader;
+        use compressed_body::CompressedBody;
+
+        let mut buf = Vec::new();
+        write_body(&mut CompressedBody(MemBody("foobar")), &mut buf).unwrap();
+
+        let mut body = String::new();
+        GzDecoder::new(ChunkedReader::new(&buf[..]))
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!("foobar", body);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn write_compressed_body_with_level() {
+        use flate2::{read::GzDecoder, Compression};
+
+        use super::super::chunked::ChunkedReader;
+        use compressed_body::CompressedBodyWithLevel;
+
+        let text = "foobar".repeat(1024);
+
+        let mut fast = Vec::new();
+        write_body(
+            &mut CompressedBodyWithLevel::new(MemBody(&text), Compression::none()),
+            &mut fast,
+        )
+        .unwrap();
+
+        let mut best = Vec::new();
+        write_body(
+            &mut CompressedBodyWithLevel::new(MemBody(&text), Compression::best()),
+            &mut best,
+        )
+        .unwrap();
+
+        assert!(best.len() < fast.len());
+
+        let mut body = String::new();
+        GzDecoder::new(ChunkedReader::new(&fast[..]))
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(text, body);
+    }
+
+    #[test]
+    fn write_body_with_trailers() {
+        use http::header::HeaderValue;
+
+        use super::super::chunked::ChunkedReader;
+
+        struct ChecksumBody(&'static [u8]);
+
+        impl BodyWriter for ChecksumBody {
+            fn kind(&mut self) -> IoResult<BodyKind> {
+                Ok(BodyKind::Chunked)
+            }
+
+            fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+                writer.write_all(self.0)
+            }
+
+            fn trailer_names(&mut self) -> Vec<HeaderName> {
+                vec![HeaderName::from_static("x-length")]
+            }
+
+            fn trailers(&mut self) -> HeaderMap {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("x-length", HeaderValue::from(self.0.len()));
+                trailers
+            }
+        }
+
+        let mut buf = Vec::new();
+        write_body(&mut ChecksumBody(b"foobar"), &mut buf).unwrap();
+
+        let mut reader = ChunkedReader::new(&buf[..]);
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        assert_eq!(b"foobar", &body[..]);
+        assert_eq!(reader.trailers()["x-length"], "6");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn write_json_body() {
+        use super::super::chunked::ChunkedReader;
+        use json_body::JsonBody;
+
+        let mut buf = Vec::new();
+        write_body(&mut JsonBody(vec![1, 2, 3]), &mut buf).unwrap();
+
+        let mut body = String::new();
+        ChunkedReader::new(&buf[..])
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!("[1,2,3]", body);
+    }
+
+    #[test]
+    fn write_streaming_body_with_flush_points() {
+        struct Recorder(Vec<u8>, Vec<usize>);
+
+        impl Write for Recorder {
+            fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> IoResult<()> {
+                self.1.push(self.0.len());
+                Ok(())
+            }
+        }
+
+        let mut lines = vec!["foo\n", "bar\n"].into_iter();
+
+        let mut writer = Recorder(Vec::new(), Vec::new());
+        StreamingBody(|writer: &mut dyn Write| match lines.next() {
+            Some(line) => {
+                writer.write_all(line.as_bytes())?;
+                Ok(ControlFlow::Continue(()))
+            }
+            None => Ok(ControlFlow::Break(())),
+        })
+        .write(&mut writer)
+        .unwrap();
+
+        assert_eq!(b"foo\nbar\n", &writer.0[..]);
+        assert_eq!(writer.1, [4, 8, 8]);
+    }
+
+    #[test]
+    fn infer_content_type_from_extension() {
+        assert_eq!("image/png", content_type_of(Path::new("foo/bar.PNG")));
+        assert_eq!(
+            "application/octet-stream",
+            content_type_of(Path::new("foo.unknown"))
+        );
+        assert_eq!(
+            "application/octet-stream",
+            content_type_of(Path::new("Makefile"))
+        );
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn hash_payload_of_replayable_and_buffered_bodies() {
+        use std::io::Cursor;
+
+        use payload_hash::{buffer_payload, PayloadHash};
+
+        const FOOBAR: &str = "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2";
+
+        assert_eq!(
+            EmptyBody.payload_hash().unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(MemBody(b"foobar").payload_hash().unwrap(), FOOBAR);
+
+        let mut body = IoBody(Cursor::new(b"foobar"));
+        assert_eq!(body.payload_hash().unwrap(), FOOBAR);
+        assert_eq!(body.payload_hash().unwrap(), FOOBAR);
+
+        let (hash, mut body) = buffer_payload(StreamingBody(|writer: &mut dyn Write| {
+            writer.write_all(b"foobar")?;
+            Ok(ControlFlow::Break(()))
+        }))
+        .unwrap();
+        assert_eq!(hash, FOOBAR);
+        assert!(matches!(body.kind().unwrap(), BodyKind::KnownLength(6)));
+    }
+
+    #[test]
+    fn write_multipart_body() {
+        use std::io::Cursor;
+
+        let mut body = MultipartBody::new().text("field", "value").seekable_file(
+            "upload",
+            "foo\"bar.txt",
+            "text/plain",
+            Cursor::new(b"foobar"),
+        );
+
+        let boundary = body.boundary().to_owned();
+        let expected = format!(
+            "--{0}\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\nvalue\r\n\
+             --{0}\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"foo%22bar.txt\"\r\nContent-Type: text/plain\r\n\r\nfoobar\r\n\
+             --{0}--\r\n",
+            boundary
+        );
+
+        let len = match body.kind().unwrap() {
+            BodyKind::KnownLength(len) => len,
+            kind => panic!("Unexpected body kind {:?}", kind),
+        };
+        assert_eq!(expected.len() as u64, len);
+
+        let mut buf = Vec::new();
+        write_body(&mut body, &mut buf).unwrap();
+        assert_eq!(expected, String::from_utf8(buf).unwrap());
+
+        let mut body = MultipartBody::new().file(
+            "upload",
+            "foo.bin",
+            "application/octet-stream",
+            &b"foobar"[..],
+        );
+        assert!(matches!(body.kind().unwrap(), BodyKind::Chunked));
     }
 }
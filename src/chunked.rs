@@ -11,26 +11,56 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+//! Encoding and decoding of the HTTP/1.1 chunked transfer coding.
 use std::convert::TryInto;
-use std::io::{BufRead, Error as IoError, ErrorKind::Other, Read, Result as IoResult, Write};
+use std::io::{
+    BufRead, Error as IoError,
+    ErrorKind::{Other, UnexpectedEof},
+    Read, Result as IoResult, Write,
+};
 
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 use httparse::{
-    parse_chunk_size, InvalidChunkSize,
+    parse_chunk_size, parse_headers, Error as ParseError, InvalidChunkSize,
     Status::{Complete, Partial},
+    EMPTY_HEADER,
 };
 
-use super::{parse::parse, Error};
+use super::{parse::parse, Error, MAX_HEADERS, MAX_PARSE_BUF_LEN};
 
+/// Writes each buffer passed to [`Write::write`] as a separate chunk.
+///
+/// [`close`](Self::close) must be called to emit the terminating chunk,
+/// otherwise the output is not a complete chunked body.
 pub struct ChunkedWriter<W>(pub W);
 
 impl<W: Write> ChunkedWriter<W> {
+    /// Writes the terminating zero-length chunk.
     pub fn close(mut self) -> IoResult<()> {
         self.0.write_all(b"0\r\n\r\n")
     }
+
+    /// Writes the terminating zero-length chunk followed by the given trailer fields.
+    pub fn close_with_trailers(mut self, trailers: &HeaderMap) -> IoResult<()> {
+        self.0.write_all(b"0\r\n")?;
+
+        for (name, value) in trailers {
+            self.0.write_all(name.as_ref())?;
+            self.0.write_all(b": ")?;
+            self.0.write_all(value.as_bytes())?;
+            self.0.write_all(b"\r\n")?;
+        }
+
+        self.0.write_all(b"\r\n")
+    }
 }
 
 impl<W: Write> Write for ChunkedWriter<W> {
     fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
         write!(self.0, "{:x}\r\n", buf.len())?;
         self.0.write_all(buf)?;
         write!(self.0, "\r\n")?;
@@ -42,10 +72,20 @@ impl<W: Write> Write for ChunkedWriter<W> {
     }
 }
 
+/// Decodes a chunked body read from the underlying reader.
+///
+/// Reading stops after the terminating zero-length chunk and the trailer fields following it
+/// without consuming any data after the final line ending. The trailer fields are available via [`trailers`](Self::trailers).
 pub struct ChunkedReader<R> {
     reader: R,
     rem: usize,
     state: State,
+    trailers: HeaderMap,
+    size: u64,
+    chunks: usize,
+    max_size: Option<u64>,
+    max_chunks: Option<usize>,
+    scratch: Vec<u8>,
 }
 
 #[derive(PartialEq)]
@@ -57,34 +97,82 @@ enum State {
 
 impl<R> ChunkedReader<R> {
     pub fn new(reader: R) -> Self {
+        Self::with_limits(reader, None, None)
+    }
+
+    /// Fails with [`Error::BodyTooLarge`] if the decoded body exceeds `max_size` bytes
+    /// and with [`Error::TooManyChunks`] if it consists of more than `max_chunks` chunks.
+    pub fn with_limits(reader: R, max_size: Option<u64>, max_chunks: Option<usize>) -> Self {
         Self {
             reader,
             rem: 0,
             state: State::Init,
+            trailers: HeaderMap::new(),
+            size: 0,
+            chunks: 0,
+            max_size,
+            max_chunks,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// The trailer fields which are empty until the whole body has been read.
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    fn check_limits(&mut self) -> IoResult<()> {
+        self.size += self.rem as u64;
+        self.chunks += 1;
+
+        if self.max_size.map_or(false, |max_size| self.size > max_size) {
+            return Err(IoError::new(Other, Error::BodyTooLarge));
         }
+
+        if self
+            .max_chunks
+            .map_or(false, |max_chunks| self.chunks > max_chunks)
+        {
+            return Err(IoError::new(Other, Error::TooManyChunks));
+        }
+
+        Ok(())
     }
 }
 
 impl<R: BufRead> BufRead for ChunkedReader<R> {
     fn fill_buf(&mut self) -> IoResult<&[u8]> {
-        if self.rem == 0 && self.state != State::Done {
+        if self.rem == 0 && self.state == State::Done {
+            return Ok(&[]);
+        }
+
+        if self.rem == 0 {
             if self.state != State::Init {
-                read_line_ending(&mut self.reader)?;
+                read_line_ending(&mut self.reader, &mut self.scratch)?;
             } else {
                 self.state = State::Next;
             }
 
-            self.rem = read_chunk_size(&mut self.reader)?;
+            self.rem = read_chunk_size(&mut self.reader, &mut self.scratch)?;
 
             if self.rem == 0 {
-                read_line_ending(&mut self.reader)?;
+                self.trailers = read_trailers(&mut self.reader, &mut self.scratch)?;
 
                 self.state = State::Done;
+
+                // Do not wait for data after the body as the connection might be kept alive.
+                return Ok(&[]);
+            } else {
+                self.check_limits()?;
             }
         }
 
         let mut buf = self.reader.fill_buf()?;
 
+        if buf.is_empty() && self.rem != 0 {
+            return Err(UnexpectedEof.into());
+        }
+
         if buf.len() > self.rem {
             buf = &buf[..self.rem];
         }
@@ -110,19 +198,51 @@ impl<R: BufRead> Read for ChunkedReader<R> {
     }
 }
 
-fn read_chunk_size<R: BufRead>(reader: R) -> IoResult<usize> {
-    parse(reader, |buf| match parse_chunk_size(buf) {
-        Ok(Complete((parsed, chunk_size))) => {
-            let chunk_size = chunk_size.try_into().unwrap();
-            Ok(Complete((parsed, chunk_size)))
+fn read_chunk_size<R: BufRead>(reader: R, scratch: &mut Vec<u8>) -> IoResult<usize> {
+    parse(
+        reader,
+        MAX_PARSE_BUF_LEN,
+        scratch,
+        |buf| match parse_chunk_size(buf) {
+            Ok(Complete((parsed, chunk_size))) => {
+                let chunk_size = chunk_size.try_into().unwrap();
+                Ok(Complete((parsed, chunk_size)))
+            }
+            Ok(Partial) => Ok(Partial),
+            Err(InvalidChunkSize) => Err(IoError::new(Other, Error::InvalidChunkSize)),
+        },
+    )
+}
+
+fn read_trailers<R: BufRead>(reader: R, scratch: &mut Vec<u8>) -> IoResult<HeaderMap> {
+    parse(reader, MAX_PARSE_BUF_LEN, scratch, |buf| {
+        let mut headers = [EMPTY_HEADER; MAX_HEADERS];
+
+        match parse_headers(buf, &mut headers) {
+            Ok(Complete((parsed, headers))) => {
+                let mut trailers = HeaderMap::new();
+
+                for header in headers {
+                    let name = HeaderName::from_bytes(header.name.as_bytes()).map_err(|_| {
+                        IoError::new(Other, Error::Httparse(ParseError::HeaderName))
+                    })?;
+                    let value = HeaderValue::from_bytes(header.value).map_err(|_| {
+                        IoError::new(Other, Error::Httparse(ParseError::HeaderValue))
+                    })?;
+
+                    trailers.append(name, value);
+                }
+
+                Ok(Complete((parsed, trailers)))
+            }
+            Ok(Partial) => Ok(Partial),
+            Err(err) => Err(IoError::new(Other, Error::Httparse(err))),
         }
-        Ok(Partial) => Ok(Partial),
-        Err(InvalidChunkSize) => Err(IoError::new(Other, Error::InvalidChunkSize)),
     })
 }
 
-fn read_line_ending<R: BufRead>(reader: R) -> IoResult<()> {
-    parse(reader, |buf| {
+fn read_line_ending<R: BufRead>(reader: R, scratch: &mut Vec<u8>) -> IoResult<()> {
+    parse(reader, MAX_PARSE_BUF_LEN, scratch, |buf| {
         if buf.starts_with(b"\r\n") {
             Ok(Complete((2, ())))
         } else if buf == b"" || buf == b"\r" {
@@ -137,7 +257,16 @@ fn read_line_ending<R: BufRead>(reader: R) -> IoResult<()> {
 mod tests {
     use super::*;
 
-    use std::io::ErrorKind::UnexpectedEof;
+    #[test]
+    fn write_chunks() {
+        let mut buf = Vec::new();
+        let mut writer = ChunkedWriter(&mut buf);
+        writer.write_all(b"foo").unwrap();
+        writer.write_all(b"").unwrap();
+        writer.write_all(b"bar").unwrap();
+        writer.close().unwrap();
+        assert_eq!(b"3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n", &buf[..]);
+    }
 
     #[test]
     fn parse_chunks() {
@@ -157,6 +286,94 @@ mod tests {
         assert_eq!(b"", &buf[..]);
     }
 
+    #[test]
+    fn parse_stops_after_last_chunk() {
+        let mut input = &b"3\r\nfoo\r\n0\r\n\r\nbar"[..];
+        let mut buf = Vec::new();
+        ChunkedReader::new(&mut input)
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(b"foo", &buf[..]);
+        assert_eq!(b"bar", input);
+    }
+
+    #[test]
+    fn parse_too_large_body() {
+        let mut buf = Vec::new();
+        let err =
+            ChunkedReader::with_limits(&b"3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n"[..], Some(5), None)
+                .read_to_end(&mut buf)
+                .unwrap_err();
+        assert_eq!(Other, err.kind());
+        assert_eq!(b"foo", &buf[..]);
+    }
+
+    #[test]
+    fn parse_too_many_chunks() {
+        let mut buf = Vec::new();
+        let err = ChunkedReader::with_limits(
+            &b"1\r\nf\r\n1\r\no\r\n1\r\no\r\n0\r\n\r\n"[..],
+            None,
+            Some(2),
+        )
+        .read_to_end(&mut buf)
+        .unwrap_err();
+        assert_eq!(Other, err.kind());
+        assert_eq!(b"fo", &buf[..]);
+    }
+
+    #[test]
+    fn parse_within_limits() {
+        let mut buf = Vec::new();
+        ChunkedReader::with_limits(&b"3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n"[..], Some(6), Some(2))
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(b"foobar", &buf[..]);
+    }
+
+    #[test]
+    fn parse_truncated_chunk() {
+        let mut buf = Vec::new();
+        let err = ChunkedReader::new(&b"6\r\nfoo"[..])
+            .read_to_end(&mut buf)
+            .unwrap_err();
+        assert_eq!(UnexpectedEof, err.kind());
+    }
+
+    #[test]
+    fn parse_trailers() {
+        let mut input = &b"3\r\nfoo\r\n0\r\nX-Checksum: abc\r\nX-Length: 3\r\n\r\nbar"[..];
+        let mut reader = ChunkedReader::new(&mut input);
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(b"foo", &buf[..]);
+
+        let trailers = reader.trailers();
+        assert_eq!(trailers["x-checksum"], "abc");
+        assert_eq!(trailers["x-length"], "3");
+
+        assert_eq!(b"bar", input);
+    }
+
+    #[test]
+    fn write_and_parse_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+
+        let mut buf = Vec::new();
+        let mut writer = ChunkedWriter(&mut buf);
+        writer.write_all(b"foobar").unwrap();
+        writer.close_with_trailers(&trailers).unwrap();
+        assert_eq!(b"6\r\nfoobar\r\n0\r\nx-checksum: abc\r\n\r\n", &buf[..]);
+
+        let mut reader = ChunkedReader::new(&buf[..]);
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        assert_eq!(b"foobar", &body[..]);
+        assert_eq!(&trailers, reader.trailers());
+    }
+
     #[test]
     fn parse_missing_line_ending() {
         let mut buf = Vec::new();
@@ -167,18 +384,33 @@ mod tests {
 
     #[test]
     fn parse_line_endings() {
-        read_line_ending(&b"\r\nfoo"[..]).unwrap();
+        read_line_ending(&b"\r\nfoo"[..], &mut Vec::new()).unwrap();
 
-        let err = read_line_ending(&b"bar"[..]).unwrap_err();
+        let err = read_line_ending(&b"bar"[..], &mut Vec::new()).unwrap_err();
         assert_eq!(Other, err.kind());
 
-        let err = read_line_ending(&b"\rbaz"[..]).unwrap_err();
+        let err = read_line_ending(&b"\rbaz"[..], &mut Vec::new()).unwrap_err();
         assert_eq!(Other, err.kind());
 
-        let err = read_line_ending(&b""[..]).unwrap_err();
+        let err = read_line_ending(&b""[..], &mut Vec::new()).unwrap_err();
         assert_eq!(UnexpectedEof, err.kind());
 
-        let err = read_line_ending(&b"\r"[..]).unwrap_err();
+        let err = read_line_ending(&b"\r"[..], &mut Vec::new()).unwrap_err();
         assert_eq!(UnexpectedEof, err.kind());
     }
+
+    #[test]
+    fn reuse_scratch_buffer_across_chunks() {
+        use std::io::BufReader;
+
+        let body = &b"3\r\nfoo\r\n3\r\nbar\r\n0\r\nfoo: bar\r\n\r\n"[..];
+        let mut reader = ChunkedReader::new(BufReader::with_capacity(1, body));
+
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+        assert_eq!("foobar", buf);
+        assert_eq!(reader.trailers()["foo"], "bar");
+
+        assert!(reader.scratch.capacity() > 0);
+    }
 }
@@ -0,0 +1,206 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::HashMap;
+use std::io::{
+    copy, sink,
+    ErrorKind::{BrokenPipe, ConnectionAborted, ConnectionReset, UnexpectedEof},
+};
+use std::sync::{atomic::AtomicBool, Arc, Mutex};
+
+use http::{
+    header::CONNECTION,
+    request::{Parts as RequestParts, Request},
+    response::Response,
+    uri::Uri,
+};
+
+use super::{
+    absolute_form, connect, handle_redirects, host_and_port, prepare_request, prepare_target,
+    read_response, resolve::RequestUri, stream::Stream, strip_credentials, write_request,
+    BodyReader, BodyWriter, ConnectWorkers, Error, Options, RequestMethod,
+};
+
+/// Sends requests reusing the connections kept alive by servers.
+///
+/// A connection is returned to a small pool keyed by scheme, host and port once the body of its last response
+/// has been read completely. Pooled connections closed by the server in the meantime are discarded when reused.
+///
+/// New connections are established using [`ConnectWorkers`] owned by the client
+/// unless [`Options::connect_workers`] is set.
+///
+/// The [`deadline`](Options::deadline) and [`idle_timeout`](Options::idle_timeout) apply to each request separately,
+/// but as the deadline is a fixed instant, it is shared by all requests sent using the client.
+///
+/// Unlike [`RequestExt::send_with_opts`](super::RequestExt::send_with_opts), [`Options::retries`] is ignored
+/// and the `Connection` header of requests is removed so that connections can be kept alive.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::Request, Client, RequestBuilderExt, ResponseExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(Default::default());
+///
+/// for _ in 0..3 {
+///     let resp = client.send(Request::get("http://httpbin.org/get").empty()?)?;
+///
+///     println!("{}", resp.into_string()?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Client<'a> {
+    opts: Options<'a>,
+    pool: Arc<Pool>,
+    workers: ConnectWorkers,
+}
+
+type Pool = Mutex<HashMap<Key, Vec<Stream>>>;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    scheme: String,
+    host: String,
+    port: u16,
+}
+
+impl Key {
+    fn new(uri: &Uri) -> Result<Self, Error> {
+        let (host, port) = host_and_port(uri)?;
+
+        Ok(Self {
+            scheme: uri.scheme_str().unwrap_or_default().to_owned(),
+            host: host.to_ascii_lowercase(),
+            port,
+        })
+    }
+}
+
+impl<'a> Client<'a> {
+    pub fn new(opts: Options<'a>) -> Self {
+        Self {
+            opts,
+            pool: Arc::default(),
+            workers: ConnectWorkers::default(),
+        }
+    }
+
+    /// Sends the request like [`RequestExt::send_with_opts`](super::RequestExt::send_with_opts),
+    /// but without asking the server to close the connection.
+    pub fn send<B: BodyWriter>(&self, req: Request<B>) -> Result<Response<BodyReader>, Error> {
+        let mut opts = self.opts;
+
+        let (mut parts, mut body) = req.into_parts();
+
+        let chunked = prepare_request(&mut parts, &mut body, &opts)?;
+        parts.headers.remove(CONNECTION);
+
+        loop {
+            prepare_target(&mut parts, &opts)?;
+
+            let mut resp = self.exchange(&parts, &mut body, chunked)?;
+
+            if let Some(jar) = opts.cookies {
+                jar.store(&parts.uri, resp.headers());
+            }
+
+            if let Some(location) = handle_redirects(&resp, &parts.uri, &mut opts)? {
+                // Draining the body allows the connection to be reused for the next request.
+                let _ = copy(resp.body_mut(), &mut sink());
+
+                strip_credentials(&mut parts.headers, &parts.uri, &location);
+
+                parts.uri = location;
+                continue;
+            }
+
+            resp.extensions_mut().insert(RequestUri(parts.uri));
+            resp.extensions_mut().insert(RequestMethod(parts.method));
+
+            return Ok(resp);
+        }
+    }
+
+    fn exchange<B: BodyWriter>(
+        &self,
+        parts: &RequestParts,
+        body: &mut B,
+        chunked: bool,
+    ) -> Result<Response<BodyReader>, Error> {
+        let key = Key::new(&parts.uri)?;
+
+        loop {
+            let idle = self.pool.lock().unwrap().get_mut(&key).and_then(Vec::pop);
+            let reused = idle.is_some();
+
+            let mut stream = match idle {
+                Some(mut stream) => {
+                    stream.restart_timeout(&self.opts)?;
+                    stream
+                }
+                None => connect(
+                    &parts.uri,
+                    &Options {
+                        connect_workers: self.opts.connect_workers.or(Some(&self.workers)),
+                        ..self.opts
+                    },
+                )?,
+            };
+
+            let released = Arc::new(AtomicBool::new(false));
+            let pool = Arc::downgrade(&self.pool);
+            let key = key.clone();
+
+            stream.release_to(
+                released.clone(),
+                Box::new(move |stream| {
+                    if let Some(pool) = pool.upgrade() {
+                        let mut pool = pool.lock().unwrap();
+                        let idle = pool.entry(key).or_default();
+
+                        if idle.len() < MAX_IDLE_PER_HOST {
+                            idle.push(stream);
+                        }
+                    }
+                }),
+            );
+
+            let res = write_request(
+                &mut stream,
+                parts,
+                body,
+                chunked,
+                absolute_form(&parts.uri, &self.opts),
+                self.opts.line_ending,
+            )
+            .and_then(|()| read_response(stream, &parts.method, &self.opts, Some(released)));
+
+            match res {
+                // The server closed the idle connection, so try the next one or a new one
+                // unless the body was already consumed and cannot be sent again.
+                Err(Error::Io(err))
+                    if reused
+                        && body.replayable()
+                        && matches!(
+                            err.kind(),
+                            UnexpectedEof | ConnectionReset | ConnectionAborted | BrokenPipe
+                        ) =>
+                {
+                    continue
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+const MAX_IDLE_PER_HOST: usize = 4;
@@ -0,0 +1,195 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::io::{BufReader, Read, Write};
+
+use http::{
+    header::HeaderMap, request::Request, response::Response, uri::Uri, Method, StatusCode, Version,
+};
+
+use super::{
+    absolute_form, connect, prepare_request, prepare_target, read_response_body,
+    read_response_head, resolve::RequestUri, stream::Stream, write_request, BodyReader, BodyWriter,
+    Error, Options, RequestMethod,
+};
+
+/// A single connection which separates reading the response head from reading its body.
+///
+/// This allows inspecting status and headers before deciding whether to read the body
+/// or to close the connection by dropping it. Redirects are not followed.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::{Request, StatusCode}, Connection, RequestBuilderExt, ResponseExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let req = Request::get("http://httpbin.org/get").empty()?;
+///
+/// let mut conn = Connection::connect(req.uri(), Default::default())?;
+/// conn.send(req)?;
+///
+/// let head = conn.read_response_head()?;
+/// if head.status() == StatusCode::OK {
+///     let body = conn.read_body()?;
+///     let resp = head.map(|()| body);
+///
+///     println!("{}", resp.into_string()?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Connection<'a> {
+    reader: BufReader<Stream>,
+    method: Method,
+    uri: Uri,
+    absolute_form: bool,
+    head: Option<(StatusCode, Version, HeaderMap)>,
+    scratch: Vec<u8>,
+    opts: Options<'a>,
+}
+
+impl<'a> Connection<'a> {
+    /// Connects to the authority of the given URI or to the configured proxy.
+    pub fn connect(uri: &Uri, opts: Options<'a>) -> Result<Self, Error> {
+        let stream = connect(uri, &opts)?;
+
+        let mut conn = Self::new(stream, opts);
+        conn.absolute_form = absolute_form(uri, &conn.opts);
+
+        Ok(conn)
+    }
+
+    /// Wraps an already established stream, e.g. an in-memory transport.
+    pub fn from_io<S>(stream: S, opts: Options<'a>) -> Self
+    where
+        S: Read + Write + Send + 'static,
+    {
+        Self::new(Stream::from_io(stream), opts)
+    }
+
+    fn new(stream: Stream, opts: Options<'a>) -> Self {
+        Self {
+            reader: BufReader::with_capacity(opts.read_buffer_size, stream),
+            method: Method::GET,
+            uri: Uri::default(),
+            absolute_form: false,
+            head: None,
+            scratch: Vec::new(),
+            opts,
+        }
+    }
+
+    /// Writes the request including its body.
+    pub fn send<B: BodyWriter>(&mut self, req: Request<B>) -> Result<(), Error> {
+        let (mut parts, mut body) = req.into_parts();
+
+        let chunked = prepare_request(&mut parts, &mut body, &self.opts)?;
+        prepare_target(&mut parts, &self.opts)?;
+
+        write_request(
+            self.reader.get_mut(),
+            &parts,
+            &mut body,
+            chunked,
+            self.absolute_form,
+            self.opts.line_ending,
+        )?;
+
+        self.method = parts.method;
+        self.uri = parts.uri;
+
+        Ok(())
+    }
+
+    /// Reads the status line and headers, but not the body.
+    pub fn read_response_head(&mut self) -> Result<Response<()>, Error> {
+        let mut head = read_response_head(&mut self.reader, &mut self.scratch, &self.opts)?;
+        head.extensions_mut().insert(RequestUri(self.uri.clone()));
+        head.extensions_mut()
+            .insert(RequestMethod(self.method.clone()));
+
+        if let Some(jar) = self.opts.cookies {
+            jar.store(&self.uri, head.headers());
+        }
+
+        self.head = Some((head.status(), head.version(), head.headers().clone()));
+
+        Ok(head)
+    }
+
+    /// Reads the body of the response whose head was read last.
+    pub fn read_body(self) -> Result<BodyReader, Error> {
+        let (status, version, headers) = self.head.ok_or(Error::MissingResponseHead)?;
+
+        read_response_body(
+            self.reader,
+            status,
+            version,
+            &headers,
+            &self.method,
+            &self.opts,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Result as IoResult};
+
+    use crate::{RequestBuilderExt, ResponseExt};
+
+    struct MockStream(Cursor<&'static [u8]>);
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    fn connection(resp: &'static [u8]) -> Connection<'static> {
+        Connection::from_io(MockStream(Cursor::new(resp)), Default::default())
+    }
+
+    #[test]
+    fn head_then_body() {
+        let mut conn = connection(b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\nnope");
+
+        conn.send(Request::get("http://localhost/").empty().unwrap())
+            .unwrap();
+
+        let head = conn.read_response_head().unwrap();
+        assert_eq!(head.status(), 404);
+        assert_eq!(head.headers()["content-length"], "4");
+
+        let body = conn.read_body().unwrap();
+        assert_eq!(head.map(|()| body).into_string().unwrap(), "nope");
+    }
+
+    #[test]
+    fn body_requires_head() {
+        let conn = connection(b"HTTP/1.1 200 OK\r\n\r\n");
+
+        assert!(matches!(conn.read_body(), Err(Error::MissingResponseHead)));
+    }
+}
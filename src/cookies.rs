@@ -0,0 +1,546 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::cmp::Reverse;
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::{
+    header::{HeaderMap, HeaderValue, SET_COOKIE},
+    uri::{Scheme, Uri},
+};
+
+/// Stores cookies set by responses and sends them with subsequent requests as specified by RFC 6265.
+///
+/// Cookies using the `Secure` attribute are only sent via `https`. The `HttpOnly` attribute only
+/// restricts access by scripts and hence does not affect which requests a cookie is sent with.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::Request, CookieJar, Options, RequestBuilderExt, RequestExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let jar = CookieJar::new();
+///
+/// let mut opts = Options::default();
+/// opts.cookies = Some(&jar);
+///
+/// Request::get("http://httpbin.org/cookies/set/foo/bar")
+///     .empty()?
+///     .send_with_opts(opts)?;
+///
+/// let uri = "http://httpbin.org/cookies".parse()?;
+/// assert_eq!(jar.get(&uri, "foo").as_deref(), Some("bar"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// To bound its memory usage, at most 50 `Set-Cookie` headers of each response are considered
+/// and at most 50 cookies are kept for each domain, evicting those set least recently.
+///
+/// There is no public suffix list, so only single-label domains like `com` are rejected
+/// as the `Domain` attribute of a cookie while e.g. `co.uk` is accepted.
+#[derive(Debug)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+    max_per_response: usize,
+    max_per_domain: usize,
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self {
+            cookies: Default::default(),
+            max_per_response: 50,
+            max_per_domain: 50,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Considers only the given number of `Set-Cookie` headers of each response, ignoring the remaining ones.
+    pub fn max_per_response(mut self, max: usize) -> Self {
+        self.max_per_response = max;
+        self
+    }
+
+    /// Keeps only the given number of cookies for each domain, evicting those set least recently.
+    pub fn max_per_domain(mut self, max: usize) -> Self {
+        self.max_per_domain = max;
+        self
+    }
+
+    /// Value of the cookie with the given name which would be sent with a request to the given URI.
+    pub fn get(&self, uri: &Uri, name: &str) -> Option<String> {
+        let now = SystemTime::now();
+
+        let cookies = self.cookies.lock().unwrap();
+
+        cookies
+            .iter()
+            .find(|cookie| cookie.name == name && !cookie.expired(now) && cookie.matches(uri))
+            .map(|cookie| cookie.value.clone())
+    }
+
+    /// Removes all cookies.
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    /// Stores the cookies set by the `Set-Cookie` headers of a response to the given URI.
+    pub(crate) fn store(&self, uri: &Uri, headers: &HeaderMap) {
+        let host = match uri.host() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return,
+        };
+
+        let now = SystemTime::now();
+
+        let mut cookies = self.cookies.lock().unwrap();
+
+        for value in headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .take(self.max_per_response)
+        {
+            let cookie = match value
+                .to_str()
+                .ok()
+                .and_then(|value| Cookie::parse(value, &host, uri.path(), now))
+            {
+                Some(cookie) => cookie,
+                None => continue,
+            };
+
+            cookies.retain(|cookie1| {
+                cookie1.name != cookie.name
+                    || cookie1.domain != cookie.domain
+                    || cookie1.path != cookie.path
+            });
+
+            // Setting an expired cookie only removes the existing one.
+            if cookie.expired(now) || self.max_per_domain == 0 {
+                continue;
+            }
+
+            let mut same_domain = cookies
+                .iter()
+                .filter(|cookie1| cookie1.domain == cookie.domain)
+                .count();
+
+            while same_domain >= self.max_per_domain {
+                let oldest = cookies
+                    .iter()
+                    .position(|cookie1| cookie1.domain == cookie.domain)
+                    .unwrap();
+
+                cookies.remove(oldest);
+                same_domain -= 1;
+            }
+
+            cookies.push(cookie);
+        }
+
+        cookies.retain(|cookie| !cookie.expired(now));
+    }
+
+    /// The `Cookie` header to send with a request to the given URI, if any cookies match.
+    pub(crate) fn header(&self, uri: &Uri) -> Option<HeaderValue> {
+        let now = SystemTime::now();
+
+        let mut cookies = self.cookies.lock().unwrap();
+
+        cookies.retain(|cookie| !cookie.expired(now));
+
+        let mut matching = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(uri))
+            .collect::<Vec<_>>();
+
+        if matching.is_empty() {
+            return None;
+        }
+
+        // Cookies with longer paths are listed first, otherwise they keep the order they were set in.
+        matching.sort_by_key(|cookie| Reverse(cookie.path.len()));
+
+        let value = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        let mut value = HeaderValue::from_str(&value).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+}
+
+impl Cookie {
+    fn parse(value: &str, host: &str, path: &str, now: SystemTime) -> Option<Self> {
+        let mut attrs = value.split(';');
+
+        let (name, value) = attrs.next()?.split_once('=')?;
+        let name = name.trim();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_owned(),
+            value: value.trim().to_owned(),
+            domain: host.to_owned(),
+            host_only: true,
+            path: default_path(path).to_owned(),
+            secure: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+
+        for attr in attrs {
+            let (key, value) = match attr.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attr.trim(), ""),
+            };
+
+            if key.eq_ignore_ascii_case("expires") {
+                if let Some(expires) = parse_date(value) {
+                    cookie.expires = Some(expires);
+                }
+            } else if key.eq_ignore_ascii_case("max-age") {
+                if let Ok(max_age1) = value.parse::<i64>() {
+                    max_age = Some(max_age1);
+                }
+            } else if key.eq_ignore_ascii_case("domain") {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+
+                if !domain.contains('.') {
+                    // A single label like `com` is treated as a public suffix and accepted only
+                    // if it names the host itself in which case the cookie stays host-only.
+                    if !domain.is_empty() && domain != host {
+                        return None;
+                    }
+                } else {
+                    // Cookies for other domains are rejected entirely.
+                    if !domain_matches(host, &domain) {
+                        return None;
+                    }
+
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+            } else if key.eq_ignore_ascii_case("path") {
+                if value.starts_with('/') {
+                    cookie.path = value.to_owned();
+                }
+            } else if key.eq_ignore_ascii_case("secure") {
+                cookie.secure = true;
+            }
+        }
+
+        // `Max-Age` takes precedence over `Expires`.
+        if let Some(max_age) = max_age {
+            cookie.expires = if max_age <= 0 {
+                Some(UNIX_EPOCH)
+            } else {
+                now.checked_add(Duration::from_secs(max_age as u64))
+            };
+        }
+
+        Some(cookie)
+    }
+
+    fn expired(&self, now: SystemTime) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
+    fn matches(&self, uri: &Uri) -> bool {
+        let host = match uri.host() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+
+        if self.secure && uri.scheme() != Some(&Scheme::HTTPS) {
+            return false;
+        }
+
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+
+        domain_matches && path_matches(uri.path(), &self.path)
+    }
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+
+    let is_addr = host.starts_with('[') || host.parse::<Ipv4Addr>().is_ok();
+
+    !is_addr
+        && host
+            .strip_suffix(domain)
+            .map_or(false, |prefix| prefix.ends_with('.'))
+}
+
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(pos) => &path[..pos],
+    }
+}
+
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    match path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Parses the date of an `Expires` attribute using the lenient algorithm of RFC 6265.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    let tokens = value
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != ':')
+        .filter(|token| !token.is_empty());
+
+    for token in tokens {
+        let digits = token.bytes().all(|byte| byte.is_ascii_digit());
+
+        if time.is_none() {
+            if let Some(time1) = parse_time(token) {
+                time = Some(time1);
+                continue;
+            }
+        }
+
+        if day.is_none() && digits && token.len() <= 2 {
+            day = token.parse::<i64>().ok();
+            continue;
+        }
+
+        if month.is_none() && token.len() >= 3 {
+            if let Some(pos) = MONTHS
+                .iter()
+                .position(|month| token[..3].eq_ignore_ascii_case(month))
+            {
+                month = Some(pos as i64 + 1);
+                continue;
+            }
+        }
+
+        if year.is_none() && digits && (2..=4).contains(&token.len()) {
+            year = token.parse::<i64>().ok();
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year < 70 {
+        year += 2000;
+    }
+
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    }
+}
+
+fn parse_time(token: &str) -> Option<(i64, i64, i64)> {
+    let mut parts = token.split(':').map(|part| {
+        if !part.is_empty() && part.len() <= 2 && part.bytes().all(|byte| byte.is_ascii_digit()) {
+            part.parse::<i64>().ok()
+        } else {
+            None
+        }
+    });
+
+    let time = (parts.next()??, parts.next()??, parts.next()??);
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(time)
+}
+
+/// Number of days since the Unix epoch of the given date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(jar: &CookieJar, uri: &str, set_cookie: &'static str) {
+        let mut headers = HeaderMap::new();
+        headers.insert(SET_COOKIE, HeaderValue::from_static(set_cookie));
+
+        jar.store(&uri.parse().unwrap(), &headers);
+    }
+
+    fn header(jar: &CookieJar, uri: &str) -> Option<String> {
+        jar.header(&uri.parse().unwrap())
+            .map(|value| value.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn caps_cookies_per_response_and_domain() {
+        let jar = CookieJar::new().max_per_response(3).max_per_domain(2);
+
+        let mut headers = HeaderMap::new();
+        for set_cookie in ["a=1", "b=2", "c=3", "d=4"] {
+            headers.append(SET_COOKIE, HeaderValue::from_static(set_cookie));
+        }
+
+        jar.store(&"http://example.com/".parse().unwrap(), &headers);
+        assert_eq!(header(&jar, "http://example.com/").unwrap(), "b=2; c=3");
+
+        store(&jar, "http://example.org/", "e=5");
+        store(&jar, "http://example.com/", "b=6");
+        assert_eq!(header(&jar, "http://example.com/").unwrap(), "c=3; b=6");
+        assert_eq!(header(&jar, "http://example.org/").unwrap(), "e=5");
+    }
+
+    #[test]
+    fn parses_dates() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784111777);
+
+        assert_eq!(parse_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(expected));
+        assert_eq!(parse_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(expected));
+        assert_eq!(parse_date("Sun Nov  6 08:49:37 1994"), Some(expected));
+
+        assert_eq!(
+            parse_date("Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(parse_date("Thu, 01 Jan 1970"), None);
+        assert_eq!(parse_date("Thu, 32 Jan 1970 00:00:00 GMT"), None);
+    }
+
+    #[test]
+    fn matches_domain_and_path() {
+        let jar = CookieJar::new();
+
+        store(&jar, "http://example.com/foo/bar", "host=1");
+        store(
+            &jar,
+            "http://www.example.com/",
+            "domain=2; Domain=.Example.com; Path=/foo",
+        );
+        store(&jar, "http://example.com/", "other=3; Domain=example.org");
+        store(&jar, "http://example.com/", "suffix=4; Domain=com");
+        store(&jar, "http://localhost/", "local=5; Domain=localhost");
+
+        assert_eq!(
+            header(&jar, "http://example.com/foo/baz").as_deref(),
+            Some("host=1; domain=2")
+        );
+        assert_eq!(header(&jar, "http://example.com/").as_deref(), None);
+        assert_eq!(
+            header(&jar, "http://www.example.com/foo").as_deref(),
+            Some("domain=2")
+        );
+        assert_eq!(
+            header(&jar, "http://www.example.com/foobar").as_deref(),
+            None
+        );
+        assert_eq!(header(&jar, "http://example.org/foo").as_deref(), None);
+        assert_eq!(header(&jar, "http://other.com/").as_deref(), None);
+        assert_eq!(
+            header(&jar, "http://localhost/").as_deref(),
+            Some("local=5")
+        );
+        assert_eq!(header(&jar, "http://sub.localhost/").as_deref(), None);
+    }
+
+    #[test]
+    fn respects_secure_attribute() {
+        let jar = CookieJar::new();
+
+        store(&jar, "https://example.com/", "foo=bar; Secure; HttpOnly");
+
+        assert_eq!(
+            header(&jar, "https://example.com/").as_deref(),
+            Some("foo=bar")
+        );
+        assert_eq!(header(&jar, "http://example.com/").as_deref(), None);
+    }
+
+    #[test]
+    fn drops_expired_cookies() {
+        let jar = CookieJar::new();
+        let uri = "http://example.com/".parse().unwrap();
+
+        store(&jar, "http://example.com/", "foo=bar; Max-Age=3600");
+        assert_eq!(jar.get(&uri, "foo").as_deref(), Some("bar"));
+
+        store(&jar, "http://example.com/", "foo=baz");
+        assert_eq!(jar.get(&uri, "foo").as_deref(), Some("baz"));
+
+        store(&jar, "http://example.com/", "foo=; Max-Age=0");
+        assert_eq!(jar.get(&uri, "foo"), None);
+
+        store(
+            &jar,
+            "http://example.com/",
+            "foo=bar; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        );
+        assert_eq!(jar.get(&uri, "foo"), None);
+        assert_eq!(header(&jar, "http://example.com/"), None);
+    }
+}
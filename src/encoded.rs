@@ -11,44 +11,100 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use std::cmp::min;
-use std::io::{BufRead, Read, Result as IoResult};
+use std::cmp::{max, min};
+use std::io::{BufRead, Error as IoError, ErrorKind::InvalidData, Read, Result as IoResult};
 
 use encoding_rs::{CoderResult, Decoder, Encoding};
 
+use super::Error;
+
 pub struct EncodedReader<R> {
     reader: R,
     decoder: Decoder,
     buf: Vec<u8>,
     pos: usize,
+    capacity: usize,
+    done: bool,
+    strict: bool,
+    had_errors: bool,
 }
 
 impl<R> EncodedReader<R> {
+    #[cfg(test)]
     pub fn new(reader: R, encoding: &'static Encoding) -> Self {
+        Self::with_capacity(reader, encoding, usize::MAX)
+    }
+
+    pub fn with_capacity(reader: R, encoding: &'static Encoding, capacity: usize) -> Self {
         Self {
             reader,
             decoder: encoding.new_decoder(),
             buf: Vec::new(),
             pos: 0,
+            capacity: max(capacity, MIN_CAPACITY),
+            done: false,
+            strict: false,
+            had_errors: false,
         }
     }
+
+    /// Fail with [`Error::MalformedText`] instead of replacing malformed sequences.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether any malformed sequences were encountered so far.
+    pub fn had_errors(&self) -> bool {
+        self.had_errors
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<R: BufRead> BufRead for EncodedReader<R> {
     fn fill_buf(&mut self) -> IoResult<&[u8]> {
-        if self.buf.len() == self.pos {
+        while self.buf.len() == self.pos && !self.done {
             let buf = self.reader.fill_buf()?;
+            let last = buf.is_empty();
 
-            let max_buf_len = self.decoder.max_utf8_buffer_length(buf.len()).unwrap();
-            self.buf.resize(max_buf_len, 0);
+            let max_buf_len = self
+                .decoder
+                .max_utf8_buffer_length(buf.len())
+                .unwrap_or(usize::MAX);
+            let mut buf_len = min(max_buf_len, self.capacity);
 
-            let last = buf.is_empty();
-            let (reason, read, written, _) = self.decoder.decode_to_utf8(buf, &mut self.buf, last);
-            assert_eq!(CoderResult::InputEmpty, reason);
+            let (reason, read, written, had_errors) = loop {
+                self.buf.resize(buf_len, 0);
+
+                let (reason, read, written, had_errors) =
+                    self.decoder.decode_to_utf8(buf, &mut self.buf, last);
+
+                // Grow the buffer if it cannot hold even a single character instead of making no progress.
+                if reason == CoderResult::OutputFull && written == 0 {
+                    buf_len = max(2 * buf_len, MIN_CAPACITY);
+                    continue;
+                }
+
+                break (reason, read, written, had_errors);
+            };
+
+            self.done = last && reason == CoderResult::InputEmpty;
 
             self.reader.consume(read);
             self.buf.truncate(written);
             self.pos = 0;
+
+            if had_errors {
+                self.had_errors = true;
+
+                if self.strict {
+                    self.buf.clear();
+                    return Err(IoError::new(InvalidData, Error::MalformedText));
+                }
+            }
         }
 
         Ok(&self.buf[self.pos..])
@@ -67,11 +123,15 @@ impl<R: BufRead> Read for EncodedReader<R> {
     }
 }
 
+const MIN_CAPACITY: usize = 16;
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use encoding_rs::WINDOWS_1252;
+    use std::io::BufReader;
+
+    use encoding_rs::{SHIFT_JIS, UTF_8, WINDOWS_1252};
 
     #[test]
     fn decode_windows_1252() {
@@ -84,4 +144,68 @@ mod tests {
 
         assert_eq!("äé", buf);
     }
+
+    #[test]
+    fn decode_with_small_capacity() {
+        let text = "äöüß".repeat(100);
+        let (buf, encoding, _) = WINDOWS_1252.encode(&text);
+
+        let mut reader = EncodedReader::with_capacity(&*buf, encoding, 0);
+
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+
+        assert_eq!(text, buf);
+    }
+
+    #[test]
+    fn decode_split_sequence() {
+        let (buf, encoding, _) = SHIFT_JIS.encode("日本");
+
+        let reader = BufReader::with_capacity(1, &*buf);
+        let mut reader = EncodedReader::new(reader, encoding);
+
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+
+        assert_eq!("日本", buf);
+    }
+
+    #[test]
+    fn grow_too_small_buffer() {
+        let (buf, encoding, _) = SHIFT_JIS.encode("日本");
+
+        let reader = BufReader::with_capacity(1, &*buf);
+        let mut reader = EncodedReader::new(reader, encoding);
+        reader.capacity = 1;
+
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+
+        assert_eq!("日本", buf);
+    }
+
+    #[test]
+    fn track_malformed_sequences() {
+        let mut reader = EncodedReader::new(&b"f\xffo"[..], UTF_8);
+
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).unwrap();
+
+        assert_eq!("f\u{fffd}o", buf);
+        assert!(reader.had_errors());
+    }
+
+    #[test]
+    fn fail_on_malformed_sequences() {
+        let mut reader = EncodedReader::new(&b"f\xffo"[..], UTF_8).strict(true);
+
+        let err = reader.read_to_string(&mut String::new()).unwrap_err();
+
+        assert_eq!(err.kind(), InvalidData);
+        assert!(matches!(
+            err.into_inner().unwrap().downcast::<Error>().as_deref(),
+            Ok(Error::MalformedText)
+        ));
+    }
 }
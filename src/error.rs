@@ -20,19 +20,50 @@ pub enum Error {
     MissingScheme,
     MissingAuthority,
     MissingStatus,
+    MissingResponseHead,
     UnsupportedProtocol,
+    UnsupportedUserinfo,
     TooManyRedirects,
+    UnexpectedStatus {
+        got: http::StatusCode,
+        allowed: Vec<http::StatusCode>,
+        headers: http::HeaderMap,
+    },
+    ConnectTimeout,
+    RedirectDowngrade,
+    LoopDetected,
+    InvalidUserAgent,
+    InvalidUsername,
+    #[cfg(feature = "encoding_rs")]
+    UnknownCharset,
+    #[cfg(feature = "encoding_rs")]
+    MalformedText,
     InvalidChunkSize,
     InvalidLineEnding,
+    ContentLengthMismatch,
+    BodyTooLarge,
+    DecompressedBodyTooLarge,
+    UnexpectedBody,
+    AmbiguousFraming,
+    TooManyChunks,
+    WireLimitExceeded,
+    #[cfg(feature = "sha2")]
+    ChecksumMismatch,
     #[cfg(feature = "rustls")]
     MissingTlsRoots,
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    TlsHandshakeTimeout,
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    ProxyConnect(http::StatusCode),
     Io(io::Error),
     Http(http::Error),
+    HttpInvalidMethod(http::method::InvalidMethod),
     HttpInvalidUri(http::uri::InvalidUri),
     HttpInvalidUriParts(http::uri::InvalidUriParts),
     HttpHeaderInvalidValue(http::header::InvalidHeaderValue),
     HttpHeaderToStr(http::header::ToStrError),
     Httparse(httparse::Error),
+    HttparseInput(httparse::Error, Vec<u8>),
     #[cfg(feature = "native-tls")]
     NativeTls(native_tls::Error),
     #[cfg(feature = "rustls")]
@@ -41,6 +72,8 @@ pub enum Error {
     InvalidServerName(String),
     #[cfg(feature = "json")]
     Json(serde_json::Error),
+    #[cfg(feature = "urlencoded")]
+    UrlEncoded(serde_urlencoded::ser::Error),
 }
 
 impl StdError for Error {
@@ -48,17 +81,21 @@ impl StdError for Error {
         match self {
             Self::Io(err) => Some(err),
             Self::Http(err) => Some(err),
+            Self::HttpInvalidMethod(err) => Some(err),
             Self::HttpInvalidUri(err) => Some(err),
             Self::HttpInvalidUriParts(err) => Some(err),
             Self::HttpHeaderInvalidValue(err) => Some(err),
             Self::HttpHeaderToStr(err) => Some(err),
             Self::Httparse(err) => Some(err),
+            Self::HttparseInput(err, _) => Some(err),
             #[cfg(feature = "native-tls")]
             Self::NativeTls(err) => Some(err),
             #[cfg(feature = "rustls")]
             Self::Tls(err) => Some(err),
             #[cfg(feature = "json")]
             Self::Json(err) => Some(err),
+            #[cfg(feature = "urlencoded")]
+            Self::UrlEncoded(err) => Some(err),
             _ => None,
         }
     }
@@ -70,19 +107,63 @@ impl fmt::Display for Error {
             Self::MissingScheme => write!(fmt, "Missing scheme"),
             Self::MissingAuthority => write!(fmt, "Missing authority"),
             Self::MissingStatus => write!(fmt, "Missing status"),
+            Self::MissingResponseHead => write!(fmt, "Missing response head"),
             Self::UnsupportedProtocol => write!(fmt, "Unsupported protocol"),
+            Self::UnsupportedUserinfo => write!(fmt, "Unsupported userinfo in URI"),
             Self::TooManyRedirects => write!(fmt, "Too many redirects"),
+            Self::UnexpectedStatus { got, allowed, .. } => {
+                write!(fmt, "Unexpected status {}, expected one of", got)?;
+
+                for status in allowed {
+                    write!(fmt, " {}", status.as_u16())?;
+                }
+
+                Ok(())
+            }
+            Self::ConnectTimeout => write!(fmt, "Connect timeout"),
+            Self::RedirectDowngrade => write!(fmt, "Redirect downgrades from HTTPS to HTTP"),
+            Self::LoopDetected => write!(fmt, "Loop detected"),
+            Self::InvalidUsername => write!(fmt, "Username must not contain a colon"),
+            Self::InvalidUserAgent => write!(fmt, "Invalid user agent"),
+            #[cfg(feature = "encoding_rs")]
+            Self::UnknownCharset => write!(fmt, "Unknown charset"),
+            #[cfg(feature = "encoding_rs")]
+            Self::MalformedText => write!(fmt, "Malformed text in declared charset"),
             Self::InvalidChunkSize => write!(fmt, "Invalid chunk size"),
             Self::InvalidLineEnding => write!(fmt, "Invalid line ending"),
+            Self::ContentLengthMismatch => write!(fmt, "Content-Length does not match body"),
+            Self::BodyTooLarge => write!(fmt, "Body too large"),
+            Self::DecompressedBodyTooLarge => write!(fmt, "Decompressed body too large"),
+            Self::UnexpectedBody => write!(fmt, "Unexpected body"),
+            Self::AmbiguousFraming => {
+                write!(fmt, "Chunked body length disagrees with Content-Length")
+            }
+            Self::TooManyChunks => write!(fmt, "Too many chunks"),
+            Self::WireLimitExceeded => write!(fmt, "Too many bytes received over the connection"),
+            #[cfg(feature = "sha2")]
+            Self::ChecksumMismatch => write!(fmt, "Checksum mismatch"),
             #[cfg(feature = "rustls")]
             Self::MissingTlsRoots => write!(fmt, "Missing TLS roots"),
+            #[cfg(any(feature = "native-tls", feature = "rustls"))]
+            Self::TlsHandshakeTimeout => write!(fmt, "TLS handshake timeout"),
+            #[cfg(any(feature = "native-tls", feature = "rustls"))]
+            Self::ProxyConnect(status) => {
+                write!(fmt, "Proxy failed to establish tunnel: {}", status)
+            }
             Self::Io(err) => write!(fmt, "I/O error: {}", err),
             Self::Http(err) => write!(fmt, "HTTP error: {}", err),
+            Self::HttpInvalidMethod(err) => write!(fmt, "HTTP invalid method: {}", err),
             Self::HttpInvalidUri(err) => write!(fmt, "HTTP invalid URI: {}", err),
             Self::HttpInvalidUriParts(err) => write!(fmt, "HTTP invalid URI parts: {}", err),
             Self::HttpHeaderInvalidValue(err) => write!(fmt, "HTTP header invalid value: {}", err),
             Self::HttpHeaderToStr(err) => write!(fmt, "HTTP header to string: {}", err),
             Self::Httparse(err) => write!(fmt, "HTTP parser error: {}", err),
+            Self::HttparseInput(err, input) => write!(
+                fmt,
+                "HTTP parser error: {} in {:?}",
+                err,
+                String::from_utf8_lossy(input)
+            ),
             #[cfg(feature = "native-tls")]
             Self::NativeTls(err) => write!(fmt, "TLS error: {}", err),
             #[cfg(feature = "rustls")]
@@ -91,6 +172,8 @@ impl fmt::Display for Error {
             Self::InvalidServerName(name) => write!(fmt, "Invalid server name: {}", name),
             #[cfg(feature = "json")]
             Self::Json(err) => write!(fmt, "JSON error: {}", err),
+            #[cfg(feature = "urlencoded")]
+            Self::UrlEncoded(err) => write!(fmt, "URL encoding error: {}", err),
         }
     }
 }
@@ -113,6 +196,12 @@ impl From<http::Error> for Error {
     }
 }
 
+impl From<http::method::InvalidMethod> for Error {
+    fn from(err: http::method::InvalidMethod) -> Self {
+        Self::HttpInvalidMethod(err)
+    }
+}
+
 impl From<http::uri::InvalidUri> for Error {
     fn from(err: http::uri::InvalidUri) -> Self {
         Self::HttpInvalidUri(err)
@@ -163,3 +252,10 @@ impl From<serde_json::Error> for Error {
         Self::Json(err)
     }
 }
+
+#[cfg(feature = "urlencoded")]
+impl From<serde_urlencoded::ser::Error> for Error {
+    fn from(err: serde_urlencoded::ser::Error) -> Self {
+        Self::UrlEncoded(err)
+    }
+}
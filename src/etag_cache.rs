@@ -0,0 +1,104 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use http::{
+    header::{HeaderValue, ETAG, IF_NONE_MATCH},
+    request::Request,
+    response::Response,
+    uri::Uri,
+    StatusCode,
+};
+
+use super::{BodyReader, BodyWriter, Error, Options, RequestExt};
+
+/// Remembers the `ETag` of responses by request URI to send conditional requests, e.g. when polling a resource.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::Request, ETagCache, Polled, RequestBuilderExt, ResponseExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cache = ETagCache::new();
+///
+/// loop {
+///     let req = Request::get("http://httpbin.org/etag/foobar").empty()?;
+///
+///     match cache.send(req, Default::default())? {
+///         Polled::Modified(resp) => println!("{}", resp.into_string()?),
+///         Polled::NotModified => (),
+///     }
+/// #   break;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ETagCache {
+    etags: Mutex<HashMap<Uri, HeaderValue>>,
+}
+
+/// Outcome of sending a request using an [`ETagCache`].
+#[allow(clippy::large_enum_variant)]
+pub enum Polled {
+    /// The resource changed or was not cached yet.
+    Modified(Response<BodyReader>),
+    /// The server responded with `304 Not Modified` to the cached `ETag`.
+    NotModified,
+}
+
+impl ETagCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the request with an `If-None-Match` header containing the cached `ETag` if any.
+    ///
+    /// The `ETag` of `200 OK` responses is cached for subsequent requests to the same URI.
+    /// An `If-None-Match` header already present in the request takes precedence.
+    pub fn send<B: BodyWriter>(&self, mut req: Request<B>, opts: Options) -> Result<Polled, Error> {
+        let uri = req.uri().clone();
+
+        if let Some(etag) = self.etags.lock().unwrap().get(&uri) {
+            req.headers_mut()
+                .entry(IF_NONE_MATCH)
+                .or_insert_with(|| etag.clone());
+        }
+
+        let resp = req.send_with_opts(opts)?;
+
+        match resp.status() {
+            StatusCode::NOT_MODIFIED => return Ok(Polled::NotModified),
+            StatusCode::OK => {
+                let mut etags = self.etags.lock().unwrap();
+
+                match resp.headers().get(ETAG) {
+                    Some(etag) => {
+                        etags.insert(uri, etag.clone());
+                    }
+                    None => {
+                        etags.remove(&uri);
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        Ok(Polled::Modified(resp))
+    }
+
+    /// Forgets the cached `ETag` of the given URI so that the next request is unconditional.
+    pub fn remove(&self, uri: &Uri) {
+        self.etags.lock().unwrap().remove(uri);
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::convert::TryFrom;
+
+use http::{
+    header::{HeaderMap, HeaderName, FORWARDED, HOST},
+    uri::{Authority, Scheme},
+};
+
+use super::{
+    header_value::{split_quoted, unquote},
+    Error,
+};
+
+/// Proxy headers which are trusted to describe the original request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustedHeader {
+    /// The `proto` and `host` parameters of the standard `Forwarded` header.
+    Forwarded,
+    /// The `X-Forwarded-Proto` header.
+    XForwardedProto,
+    /// The `X-Forwarded-Host` header.
+    XForwardedHost,
+}
+
+/// Scheme and host of the original request as seen by the client of a proxy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EffectiveOrigin {
+    pub scheme: Option<Scheme>,
+    pub host: Option<Authority>,
+}
+
+/// Determines the effective scheme and host of a request which passed through proxies.
+///
+/// Only the given headers are considered and earlier ones take precedence. Of each header,
+/// only the last entry is used as it was appended by the trusted proxy whereas earlier ones
+/// might have been sent by the client itself.
+/// If no trusted header specifies the host, the `Host` header is used.
+///
+/// ```
+/// # use zeptohttpc::{effective_origin, http::{uri::Scheme, HeaderMap, HeaderValue}, TrustedHeader};
+/// let mut headers = HeaderMap::new();
+/// headers.insert("host", HeaderValue::from_static("backend:8080"));
+/// headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+///
+/// let origin = effective_origin(&headers, &[TrustedHeader::XForwardedProto]).unwrap();
+///
+/// assert_eq!(origin.scheme, Some(Scheme::HTTPS));
+/// assert_eq!(origin.host.unwrap(), "backend:8080");
+/// ```
+pub fn effective_origin(
+    headers: &HeaderMap,
+    trusted: &[TrustedHeader],
+) -> Result<EffectiveOrigin, Error> {
+    let mut origin = EffectiveOrigin::default();
+
+    for header in trusted {
+        match header {
+            TrustedHeader::Forwarded => {
+                if let Some(elem) = last_value(headers, &FORWARDED)? {
+                    for pair in split_quoted(elem, b';') {
+                        let mut pair = pair.splitn(2, '=');
+
+                        let key = pair.next().unwrap_or_default().trim();
+                        let value = unquote(pair.next().unwrap_or_default().trim());
+
+                        if key.eq_ignore_ascii_case("proto") && origin.scheme.is_none() {
+                            origin.scheme = Some(Scheme::try_from(value)?);
+                        } else if key.eq_ignore_ascii_case("host") && origin.host.is_none() {
+                            origin.host = Some(Authority::try_from(value)?);
+                        }
+                    }
+                }
+            }
+            TrustedHeader::XForwardedProto => {
+                if let Some(value) = last_value(headers, &X_FORWARDED_PROTO)? {
+                    if origin.scheme.is_none() {
+                        origin.scheme = Some(Scheme::try_from(value.trim())?);
+                    }
+                }
+            }
+            TrustedHeader::XForwardedHost => {
+                if let Some(value) = last_value(headers, &X_FORWARDED_HOST)? {
+                    if origin.host.is_none() {
+                        origin.host = Some(Authority::try_from(value.trim())?);
+                    }
+                }
+            }
+        }
+    }
+
+    if origin.host.is_none() {
+        if let Some(host) = headers.get(HOST) {
+            origin.host = Some(Authority::try_from(host.to_str()?)?);
+        }
+    }
+
+    Ok(origin)
+}
+
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+fn last_value<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Result<Option<&'a str>, Error> {
+    match headers.get_all(name).iter().next_back() {
+        Some(value) => Ok(split_quoted(value.to_str()?, b',').last()),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::header::HeaderValue;
+
+    #[test]
+    fn forwarded_uses_last_element() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            FORWARDED,
+            HeaderValue::from_static(
+                r#"for=198.51.100.17;proto=http, for=192.0.2.60;proto=https;host="example.com:8443""#,
+            ),
+        );
+
+        let origin = effective_origin(&headers, &[TrustedHeader::Forwarded]).unwrap();
+        assert_eq!(origin.scheme, Some(Scheme::HTTPS));
+        assert_eq!(origin.host.unwrap(), "example.com:8443");
+    }
+
+    #[test]
+    fn ignores_elements_spoofed_by_client() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            FORWARDED,
+            HeaderValue::from_static("proto=https;host=evil.example"),
+        );
+        headers.append(
+            FORWARDED,
+            HeaderValue::from_static("for=192.0.2.60;proto=http;host=example.com"),
+        );
+        headers.insert(
+            X_FORWARDED_HOST,
+            HeaderValue::from_static("evil.example, example.com"),
+        );
+
+        let origin = effective_origin(&headers, &[TrustedHeader::Forwarded]).unwrap();
+        assert_eq!(origin.scheme, Some(Scheme::HTTP));
+        assert_eq!(origin.host.unwrap(), "example.com");
+
+        let origin = effective_origin(&headers, &[TrustedHeader::XForwardedHost]).unwrap();
+        assert_eq!(origin.host.unwrap(), "example.com");
+    }
+
+    #[test]
+    fn earlier_headers_take_precedence() {
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED, HeaderValue::from_static("proto=http"));
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static("http, https"));
+        headers.insert(X_FORWARDED_HOST, HeaderValue::from_static("example.com"));
+
+        let origin = effective_origin(
+            &headers,
+            &[
+                TrustedHeader::XForwardedProto,
+                TrustedHeader::XForwardedHost,
+                TrustedHeader::Forwarded,
+            ],
+        )
+        .unwrap();
+        assert_eq!(origin.scheme, Some(Scheme::HTTPS));
+        assert_eq!(origin.host.unwrap(), "example.com");
+    }
+
+    #[test]
+    fn untrusted_headers_are_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, HeaderValue::from_static("backend"));
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static("https"));
+        headers.insert(X_FORWARDED_HOST, HeaderValue::from_static("example.com"));
+
+        let origin = effective_origin(&headers, &[]).unwrap();
+        assert_eq!(origin.scheme, None);
+        assert_eq!(origin.host.unwrap(), "backend");
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Entry points for fuzzing which run the same parsing and decoding code as when sending requests.
+use std::io::{sink, Cursor, Read, Result as IoResult, Write};
+
+use http::response::Response;
+
+use super::{chunked::ChunkedReader, Connection, Error};
+
+/// Parses the input as the response to a `GET` request using default options and reads its whole body.
+pub fn parse_response(input: &[u8]) -> Result<Response<Vec<u8>>, Error> {
+    let mut conn = Connection::from_io(Input(Cursor::new(input.to_vec())), Default::default());
+
+    let head = conn.read_response_head()?;
+
+    let mut body = Vec::new();
+    conn.read_body()?.read_to_end(&mut body)?;
+
+    Ok(head.map(|()| body))
+}
+
+/// Decodes the input as a chunked body.
+pub fn decode_chunked(input: &[u8]) -> IoResult<Vec<u8>> {
+    let mut body = Vec::new();
+    ChunkedReader::new(input).read_to_end(&mut body)?;
+
+    Ok(body)
+}
+
+struct Input(Cursor<Vec<u8>>);
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for Input {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        sink().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_complete_response() {
+        let resp = parse_response(b"HTTP/1.1 200 Ok\r\nContent-Length: 3\r\n\r\nfoo").unwrap();
+
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.body(), b"foo");
+    }
+
+    #[test]
+    fn parse_truncated_response() {
+        parse_response(b"HTTP/1.1 200 Ok\r\nContent-").unwrap_err();
+    }
+
+    #[test]
+    fn decode_chunks() {
+        let body = decode_chunked(b"3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n").unwrap();
+        assert_eq!(body, b"foobar");
+
+        decode_chunked(b"zz\r\n").unwrap_err();
+    }
+}
@@ -11,24 +11,155 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use std::io::ErrorKind::TimedOut;
+use std::collections::VecDeque;
+use std::io::{Error as IoError, ErrorKind::TimedOut};
 use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
 use std::str::FromStr;
-use std::sync::mpsc::channel;
+use std::sync::{
+    mpsc::{channel, RecvTimeoutError},
+    Arc, Condvar, Mutex,
+};
 use std::thread::spawn;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use super::{Error, Options};
 
+/// Reusable threads on which name resolution and connection attempts run.
+///
+/// Each attempt still gets a thread of its own so that a stalled attempt never delays the others,
+/// but threads are kept around for a while after their attempt finished instead of exiting.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::Request, ConnectWorkers, Options, RequestBuilderExt, RequestExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let workers = ConnectWorkers::default();
+///
+/// let mut opts = Options::default();
+/// opts.connect_workers = Some(&workers);
+///
+/// for _ in 0..3 {
+///     Request::get("http://httpbin.org/get").empty()?.send_with_opts(opts)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ConnectWorkers {
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: VecDeque<Job>,
+    idle: usize,
+    shutdown: bool,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+impl ConnectWorkers {
+    fn execute(&self, job: Job) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        // Only queue the job if there is an idle worker which has not yet been handed another job.
+        if state.idle > state.jobs.len() {
+            state.jobs.push_back(job);
+            self.shared.cond.notify_one();
+        } else {
+            drop(state);
+
+            let shared = self.shared.clone();
+            spawn(move || shared.run(job));
+        }
+    }
+}
+
+impl Drop for ConnectWorkers {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.cond.notify_all();
+    }
+}
+
+impl Shared {
+    fn run(&self, mut job: Job) {
+        loop {
+            job();
+
+            let mut state = self.state.lock().unwrap();
+
+            if state.shutdown || state.idle >= MAX_IDLE_WORKERS {
+                return;
+            }
+
+            state.idle += 1;
+
+            job = loop {
+                if let Some(job) = state.jobs.pop_front() {
+                    state.idle -= 1;
+                    break job;
+                }
+
+                if state.shutdown {
+                    state.idle -= 1;
+                    return;
+                }
+
+                let (state1, res) = self.cond.wait_timeout(state, IDLE_TIMEOUT).unwrap();
+                state = state1;
+
+                if res.timed_out() && state.jobs.is_empty() {
+                    state.idle -= 1;
+                    return;
+                }
+            };
+        }
+    }
+}
+
+const MAX_IDLE_WORKERS: usize = 8;
+
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn execute(opts: &Options, job: impl FnOnce() + Send + 'static) {
+    match opts.connect_workers {
+        Some(workers) => workers.execute(Box::new(job)),
+        None => {
+            spawn(job);
+        }
+    }
+}
+
 pub fn connect(host: &str, port: u16, opts: &Options) -> Result<TcpStream, Error> {
     let timeout = opts.connect_timeout;
     let delay = opts.connect_delay;
-    let deadline = opts.deadline;
+    let connect_deadline = opts
+        .overall_connect_timeout
+        .map(|timeout| Instant::now() + timeout);
+    let deadline = match (opts.deadline, connect_deadline) {
+        (Some(deadline), Some(connect_deadline)) => Some(deadline.min(connect_deadline)),
+        (deadline, connect_deadline) => deadline.or(connect_deadline),
+    };
 
-    let mut addrs = resolve_addrs(host, port)?;
+    let mut addrs = match connect_deadline {
+        Some(connect_deadline) => resolve_addrs_until(host, port, connect_deadline, opts)?,
+        None => resolve_addrs(host, port)?,
+    };
 
     if let [(_prio, addr)] = addrs.as_slice() {
-        return TcpStream::connect_timeout(addr, timeout).map_err(Error::from);
+        let res = match remaining(connect_deadline) {
+            None => TcpStream::connect_timeout(addr, timeout),
+            Some(Some(timeout1)) => TcpStream::connect_timeout(addr, timeout.min(timeout1)),
+            Some(None) => return Err(Error::ConnectTimeout),
+        };
+
+        return res.map_err(|err| connect_err(err, connect_deadline));
     }
 
     addrs
@@ -52,9 +183,8 @@ pub fn connect(host: &str, port: u16, opts: &Options) -> Result<TcpStream, Error
     for (_prio, addr) in addrs {
         let tx = tx.clone();
 
-        spawn(move || {
-            let res = match deadline.map(|deadline| deadline.checked_duration_since(Instant::now()))
-            {
+        execute(opts, move || {
+            let res = match remaining(deadline) {
                 None => TcpStream::connect_timeout(&addr, timeout),
                 Some(Some(timeout1)) => TcpStream::connect_timeout(&addr, timeout.min(timeout1)),
                 Some(None) => Err(TimedOut.into()),
@@ -63,6 +193,12 @@ pub fn connect(host: &str, port: u16, opts: &Options) -> Result<TcpStream, Error
             let _ = tx.send(res);
         });
 
+        let delay = match remaining(connect_deadline) {
+            None => delay,
+            Some(Some(timeout1)) => delay.min(timeout1),
+            Some(None) => return Err(Error::ConnectTimeout),
+        };
+
         if let Ok(res) = rx.recv_timeout(delay) {
             match res {
                 Ok(stream) => return Ok(stream),
@@ -73,14 +209,64 @@ pub fn connect(host: &str, port: u16, opts: &Options) -> Result<TcpStream, Error
 
     drop(tx);
 
-    for res in rx.iter() {
+    loop {
+        let res = match remaining(connect_deadline) {
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(Some(timeout1)) => rx.recv_timeout(timeout1),
+            Some(None) => Err(RecvTimeoutError::Timeout),
+        };
+
         match res {
-            Ok(stream) => return Ok(stream),
-            Err(err) => first_err = first_err.or(Some(err)),
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => first_err = first_err.or(Some(err)),
+            Err(RecvTimeoutError::Timeout) => return Err(Error::ConnectTimeout),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Err(connect_err(first_err.unwrap(), connect_deadline))
+}
+
+/// Returns `None` without deadline and `Some(None)` if the deadline has elapsed.
+fn remaining(deadline: Option<Instant>) -> Option<Option<Duration>> {
+    deadline.map(|deadline| {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+
+        if timeout != Duration::ZERO {
+            Some(timeout)
+        } else {
+            None
         }
+    })
+}
+
+fn connect_err(err: IoError, connect_deadline: Option<Instant>) -> Error {
+    if err.kind() == TimedOut && remaining(connect_deadline) == Some(None) {
+        Error::ConnectTimeout
+    } else {
+        err.into()
     }
+}
 
-    Err(first_err.unwrap().into())
+fn resolve_addrs_until(
+    host: &str,
+    port: u16,
+    deadline: Instant,
+    opts: &Options,
+) -> Result<Vec<(usize, SocketAddr)>, Error> {
+    let timeout = remaining(Some(deadline))
+        .flatten()
+        .ok_or(Error::ConnectTimeout)?;
+
+    let host = host.to_owned();
+    let (tx, rx) = channel();
+
+    execute(opts, move || {
+        let _ = tx.send(resolve_addrs(&host, port));
+    });
+
+    rx.recv_timeout(timeout)
+        .map_err(|_| Error::ConnectTimeout)?
 }
 
 fn resolve_addrs(host: &str, port: u16) -> Result<Vec<(usize, SocketAddr)>, Error> {
@@ -101,6 +287,57 @@ mod tests {
     use super::*;
 
     use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::sync::Barrier;
+    use std::thread::{current, ThreadId};
+
+    #[test]
+    fn connect_fails_after_overall_timeout() {
+        let opts = Options {
+            overall_connect_timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+
+        match connect("localhost", 80, &opts) {
+            Err(Error::ConnectTimeout) => (),
+            Err(err) => panic!("Unexpected error: {}", err),
+            Ok(_stream) => panic!("Unexpected connection"),
+        }
+    }
+
+    #[test]
+    fn workers_are_reused() {
+        let workers = ConnectWorkers::default();
+
+        let run = || {
+            let (tx, rx) = channel::<ThreadId>();
+            workers.execute(Box::new(move || tx.send(current().id()).unwrap()));
+            let id = rx.recv().unwrap();
+
+            // Wait until the worker is idle again.
+            while workers.shared.state.lock().unwrap().idle == 0 {
+                std::thread::yield_now();
+            }
+
+            id
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn workers_run_jobs_concurrently() {
+        let workers = ConnectWorkers::default();
+        let barrier = Arc::new(Barrier::new(3));
+
+        for _ in 0..2 {
+            let barrier = barrier.clone();
+            workers.execute(Box::new(move || {
+                barrier.wait();
+            }));
+        }
+
+        barrier.wait();
+    }
 
     #[test]
     fn resolve_domain() {
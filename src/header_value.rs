@@ -0,0 +1,48 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+/// Splits the value at the delimiter, skipping delimiters inside quoted strings.
+pub fn split_quoted(value: &str, delim: u8) -> impl Iterator<Item = &str> {
+    let mut rest = Some(value);
+
+    std::iter::from_fn(move || {
+        let value = rest?;
+
+        let mut quoted = false;
+        let mut escaped = false;
+
+        for (pos, byte) in value.bytes().enumerate() {
+            if escaped {
+                escaped = false;
+            } else if quoted && byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                quoted = !quoted;
+            } else if !quoted && byte == delim {
+                rest = Some(&value[pos + 1..]);
+                return Some(&value[..pos]);
+            }
+        }
+
+        rest = None;
+        Some(value)
+    })
+}
+
+/// Strips the quotes surrounding a quoted string without resolving escapes.
+pub fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
@@ -0,0 +1,210 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::io::{BufRead, ErrorKind::UnexpectedEof, Result as IoResult};
+use std::marker::PhantomData;
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde_json::{de::from_slice, Error as JsonError};
+
+use super::Error;
+
+/// Iterates over the elements of a top-level JSON array, deserializing them one at a time.
+///
+/// Only the raw bytes of the current element are buffered. Malformed elements yield an error
+/// without ending the iteration whereas malformed framing or I/O errors end it.
+pub struct JsonArrayStream<R, T> {
+    reader: R,
+    state: State,
+    buf: Vec<u8>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    Element,
+    Done,
+}
+
+impl<R: BufRead, T: DeserializeOwned> JsonArrayStream<R, T> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Start,
+            buf: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Reads the raw bytes of the next element into `buf`, returning `false` at the end of the array.
+    fn next_element(&mut self) -> Result<bool, Error> {
+        if self.state == State::Start {
+            if self.next_non_whitespace()? != Some(b'[') {
+                return Err(JsonError::custom("expected `[`").into());
+            }
+
+            if self.peek_non_whitespace()? == Some(b']') {
+                self.reader.consume(1);
+                return Ok(false);
+            }
+
+            self.state = State::Element;
+        }
+
+        self.buf.clear();
+
+        let mut depth = 0_usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        let delimiter = loop {
+            let byte = self.next_byte()?.ok_or(UnexpectedEof)?;
+
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth == 0 => break byte,
+                    b']' | b'}' => depth -= 1,
+                    b',' if depth == 0 => break byte,
+                    _ => (),
+                }
+            }
+
+            self.buf.push(byte);
+        };
+
+        match delimiter {
+            b',' => (),
+            b']' => self.state = State::Done,
+            _ => return Err(JsonError::custom("expected `,` or `]`").into()),
+        }
+
+        Ok(true)
+    }
+
+    fn next_byte(&mut self) -> IoResult<Option<u8>> {
+        let byte = self.reader.fill_buf()?.first().copied();
+
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+
+        Ok(byte)
+    }
+
+    fn peek_non_whitespace(&mut self) -> IoResult<Option<u8>> {
+        loop {
+            match self.reader.fill_buf()?.first().copied() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.reader.consume(1),
+                byte => return Ok(byte),
+            }
+        }
+    }
+
+    fn next_non_whitespace(&mut self) -> IoResult<Option<u8>> {
+        let byte = self.peek_non_whitespace()?;
+
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+
+        Ok(byte)
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for JsonArrayStream<R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == State::Done {
+            return None;
+        }
+
+        match self.next_element() {
+            Ok(true) => Some(from_slice(&self.buf).map_err(Error::Json)),
+            Ok(false) => {
+                self.state = State::Done;
+                None
+            }
+            Err(err) => {
+                self.state = State::Done;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    fn collect(json: &str) -> Vec<Result<Value, Error>> {
+        JsonArrayStream::new(json.as_bytes()).collect()
+    }
+
+    #[test]
+    fn yields_elements() {
+        let elems = collect(r#" [1, "a,]\"}", {"b": [2, {}]}, [], null ] "#);
+
+        let elems = elems.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(
+            elems,
+            [
+                json!(1),
+                json!("a,]\"}"),
+                json!({"b": [2, {}]}),
+                json!([]),
+                json!(null)
+            ]
+        );
+    }
+
+    #[test]
+    fn yields_nothing_for_empty_array() {
+        assert!(collect(" [ ] ").is_empty());
+    }
+
+    #[test]
+    fn continues_after_malformed_element() {
+        let elems = collect("[1, {\"a\": x}, 3]");
+
+        assert_eq!(elems.len(), 3);
+        assert_eq!(elems[0].as_ref().unwrap(), &json!(1));
+        assert!(matches!(elems[1], Err(Error::Json(_))));
+        assert_eq!(elems[2].as_ref().unwrap(), &json!(3));
+    }
+
+    #[test]
+    fn stops_after_malformed_framing() {
+        let elems = collect("{\"a\": 1}");
+        assert_eq!(elems.len(), 1);
+        assert!(matches!(elems[0], Err(Error::Json(_))));
+
+        let elems = collect("[1, 2");
+        assert_eq!(elems.len(), 2);
+        assert_eq!(elems[0].as_ref().unwrap(), &json!(1));
+        assert!(matches!(elems[1], Err(Error::Io(_))));
+    }
+}
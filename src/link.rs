@@ -0,0 +1,229 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use http::{
+    header::{HeaderMap, LINK},
+    uri::Uri,
+};
+
+use super::resolve::resolve;
+
+/// A link from a `Link` header as specified by RFC 8288.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The target resolved against the URI of the request which produced the response.
+    pub uri: Uri,
+    /// The relation types, e.g. `next`, separated by spaces.
+    pub rel: Option<String>,
+    /// The remaining parameters with lowercase names and unquoted values.
+    pub params: Vec<(String, String)>,
+}
+
+impl Link {
+    /// Whether the given relation type is one of those of the link, ignoring ASCII case.
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rel.as_deref().map_or(false, |rels| {
+            rels.split_ascii_whitespace()
+                .any(|rel1| rel1.eq_ignore_ascii_case(rel))
+        })
+    }
+}
+
+/// Parses all `Link` headers, skipping links whose target is not a valid URI.
+///
+/// Parsing a header stops at the first malformed link.
+pub fn parse_links(headers: &HeaderMap, base: Option<&Uri>) -> Vec<Link> {
+    let mut links = Vec::new();
+
+    for value in headers.get_all(LINK) {
+        if let Ok(value) = value.to_str() {
+            parse_header(value, base, &mut links);
+        }
+    }
+
+    links
+}
+
+fn parse_header(mut value: &str, base: Option<&Uri>, links: &mut Vec<Link>) {
+    loop {
+        value = value.trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+
+        if value.is_empty() {
+            return;
+        }
+
+        let (target, rest) = match value
+            .strip_prefix('<')
+            .and_then(|value| value.split_once('>'))
+        {
+            Some(link) => link,
+            None => return,
+        };
+        value = rest;
+
+        let mut rel = None;
+        let mut params = Vec::new();
+
+        loop {
+            value = value.trim_start();
+
+            value = match value.strip_prefix(';') {
+                Some(rest) => rest.trim_start(),
+                None => break,
+            };
+
+            let end = value
+                .find(|c: char| c == '=' || c == ';' || c == ',' || c.is_ascii_whitespace())
+                .unwrap_or(value.len());
+            let name = value[..end].to_ascii_lowercase();
+            value = value[end..].trim_start();
+
+            let param = match value.strip_prefix('=') {
+                Some(rest) => {
+                    let (param, rest) = match parse_value(rest.trim_start()) {
+                        Some(value) => value,
+                        None => return,
+                    };
+                    value = rest;
+                    param
+                }
+                None => String::new(),
+            };
+
+            // Occurrences of `rel` after the first one are ignored as required by RFC 8288.
+            if name == "rel" {
+                if rel.is_none() {
+                    rel = Some(param);
+                }
+            } else if !name.is_empty() {
+                params.push((name, param));
+            }
+        }
+
+        if !value.is_empty() && !value.starts_with(',') {
+            return;
+        }
+
+        let uri = match base {
+            Some(base) => resolve(base, target).ok(),
+            None => target.parse().ok(),
+        };
+
+        if let Some(uri) = uri {
+            links.push(Link { uri, rel, params });
+        }
+    }
+}
+
+fn parse_value(value: &str) -> Option<(String, &str)> {
+    match value.strip_prefix('"') {
+        Some(value) => {
+            let mut param = String::new();
+            let mut escaped = false;
+
+            for (pos, c) in value.char_indices() {
+                if escaped {
+                    param.push(c);
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '"' {
+                    return Some((param, &value[pos + 1..]));
+                } else {
+                    param.push(c);
+                }
+            }
+
+            None
+        }
+        None => {
+            let end = value
+                .find(|c: char| c == ';' || c == ',' || c.is_ascii_whitespace())
+                .unwrap_or(value.len());
+
+            Some((value[..end].to_owned(), &value[end..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::header::HeaderValue;
+
+    fn links(values: &[&'static str]) -> Vec<Link> {
+        let mut headers = HeaderMap::new();
+
+        for value in values {
+            headers.append(LINK, HeaderValue::from_static(value));
+        }
+
+        let base = "http://example.com/items?page=2".parse::<Uri>().unwrap();
+
+        parse_links(&headers, Some(&base))
+    }
+
+    #[test]
+    fn parses_multiple_links_per_header() {
+        let links =
+            links(&[r#"</items?page=3>; rel="next", <http://example.com/items?page=1>; rel=prev"#]);
+
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].uri, "http://example.com/items?page=3");
+        assert!(links[0].has_rel("next"));
+        assert_eq!(links[1].uri, "http://example.com/items?page=1");
+        assert!(links[1].has_rel("prev"));
+    }
+
+    #[test]
+    fn parses_multiple_headers() {
+        let links = links(&["<?page=3>; rel=next", "<../first>; rel=\"first\""]);
+
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].uri, "http://example.com/items?page=3");
+        assert_eq!(links[1].uri, "http://example.com/first");
+        assert!(links[1].has_rel("FIRST"));
+    }
+
+    #[test]
+    fn parses_params() {
+        let links = links(&[
+            r#"<https://example.org/a,b;c>; rel="next last"; title="say \"hi\", ok"; Type=text/html; rel=prev; crossorigin"#,
+        ]);
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].uri, "https://example.org/a,b;c");
+        assert_eq!(links[0].rel.as_deref(), Some("next last"));
+        assert!(links[0].has_rel("last"));
+        assert!(!links[0].has_rel("prev"));
+        assert_eq!(
+            links[0].params,
+            vec![
+                ("title".to_owned(), "say \"hi\", ok".to_owned()),
+                ("type".to_owned(), "text/html".to_owned()),
+                ("crossorigin".to_owned(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_malformed_link() {
+        let links = links(&["<next>; rel=next, garbage, <last>; rel=last", "<other>"]);
+
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].uri, "http://example.com/next");
+        assert_eq!(links[1].uri, "http://example.com/other");
+        assert_eq!(links[1].rel, None);
+    }
+}
@@ -5,9 +5,15 @@ use std::io::{
 
 use httparse::Status::{self, Complete, Partial};
 
-use super::MAX_PARSE_BUF_LEN;
-
-pub fn parse<R, P, T, E>(mut reader: R, parser: P) -> Result<T, E>
+/// Parses using the buffer of `reader` if possible, failing if more than `max_len` bytes are required.
+///
+/// Otherwise, the input is accumulated in `scratch` which is cleared but keeps its allocation.
+pub fn parse<R, P, T, E>(
+    mut reader: R,
+    max_len: usize,
+    scratch: &mut Vec<u8>,
+    parser: P,
+) -> Result<T, E>
 where
     R: BufRead,
     P: Fn(&[u8]) -> Result<Status<(usize, T)>, E>,
@@ -15,42 +21,130 @@ where
 {
     let buf = reader.fill_buf()?;
     if let Complete((parsed, val)) = parser(buf)? {
+        if parsed > max_len {
+            return Err(max_len_reached().into());
+        }
+
         reader.consume(parsed);
         return Ok(val);
     }
 
-    parse_buffered(reader, parser)
+    parse_buffered(reader, max_len, scratch, parser)
 }
 
 #[cold]
-fn parse_buffered<R, P, T, E>(mut reader: R, parser: P) -> Result<T, E>
+fn parse_buffered<R, P, T, E>(
+    mut reader: R,
+    max_len: usize,
+    buf1: &mut Vec<u8>,
+    parser: P,
+) -> Result<T, E>
 where
     R: BufRead,
     P: Fn(&[u8]) -> Result<Status<(usize, T)>, E>,
     E: From<IoError>,
 {
-    let mut buf1 = Vec::new();
+    buf1.clear();
     loop {
         let buf = reader.fill_buf()?;
         if buf.is_empty() {
             return Err(IoError::from(UnexpectedEof).into());
         }
-        if buf1.len() + buf.len() > MAX_PARSE_BUF_LEN {
-            return Err(IoError::new(Other, "Maximum parse buffer length reached").into());
-        }
-        buf1.extend_from_slice(buf);
+        // Bytes beyond the limit cannot be part of an acceptable head, but might belong to the body.
+        let len = buf.len().min(max_len - buf1.len());
+        buf1.extend_from_slice(&buf[..len]);
 
-        match parser(&buf1)? {
+        match parser(buf1)? {
             Complete((parsed, val)) => {
-                let amt = parsed - (buf1.len() - buf.len());
+                let amt = parsed - (buf1.len() - len);
                 reader.consume(amt);
 
                 return Ok(val);
             }
             Partial => {
-                let amt = buf.len();
-                reader.consume(amt);
+                reader.consume(len);
+
+                if buf1.len() == max_len {
+                    return Err(max_len_reached().into());
+                }
+            }
+        }
+    }
+}
+
+fn max_len_reached() -> IoError {
+    IoError::new(Other, "Maximum parse buffer length reached")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Result as IoResult};
+
+    use httparse::{Response, EMPTY_HEADER};
+
+    /// Yields the given segments one at a time from `fill_buf`.
+    struct Segments(Vec<Vec<u8>>);
+
+    impl Read for Segments {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            let read = self.fill_buf()?.read(buf)?;
+            self.consume(read);
+            Ok(read)
+        }
+    }
+
+    impl BufRead for Segments {
+        fn fill_buf(&mut self) -> IoResult<&[u8]> {
+            Ok(self.0.first().map_or(&[], |segment| &segment[..]))
+        }
+
+        fn consume(&mut self, amt: usize) {
+            if let Some(segment) = self.0.first_mut() {
+                segment.drain(..amt);
+
+                if segment.is_empty() {
+                    self.0.remove(0);
+                }
             }
         }
     }
+
+    fn parse_status(reader: &mut Segments, max_len: usize) -> IoResult<u16> {
+        parse(reader, max_len, &mut Vec::new(), |buf| {
+            let mut headers = [EMPTY_HEADER; 4];
+            let mut resp = Response::new(&mut headers);
+
+            match resp.parse(buf).map_err(|err| IoError::new(Other, err))? {
+                Complete(parsed) => Ok(Complete((parsed, resp.code.unwrap()))),
+                Partial => Ok(Partial),
+            }
+        })
+    }
+
+    #[test]
+    fn body_bytes_do_not_count_towards_limit() {
+        let mut reader = Segments(vec![
+            b"HTTP/1.1 200 OK\r\n".to_vec(),
+            [&b"Content-Length: 1000\r\n\r\n"[..], &[b'x'; 1000]].concat(),
+        ]);
+
+        assert_eq!(parse_status(&mut reader, 64).unwrap(), 200);
+
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        assert_eq!(body, [b'x'; 1000]);
+    }
+
+    #[test]
+    fn fails_if_head_exceeds_limit() {
+        let mut reader = Segments(vec![
+            b"HTTP/1.1 200 OK\r\n".to_vec(),
+            [&b"X-Padding: "[..], &[b'x'; 100], b"\r\n\r\n"].concat(),
+        ]);
+
+        let err = parse_status(&mut reader, 64).unwrap_err();
+        assert_eq!(err.to_string(), max_len_reached().to_string());
+    }
 }
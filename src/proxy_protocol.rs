@@ -0,0 +1,143 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::io::{Result as IoResult, Write};
+use std::net::{SocketAddr, TcpStream};
+
+/// Version of the PROXY protocol header sent before the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocol {
+    /// The human-readable text format.
+    V1,
+    /// The binary format.
+    V2,
+}
+
+/// Writes the PROXY protocol header describing the given connection.
+pub fn write_header(mut stream: &TcpStream, version: ProxyProtocol) -> IoResult<()> {
+    let header = header(version, stream.local_addr()?, stream.peer_addr()?);
+
+    stream.write_all(&header)
+}
+
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+fn header(version: ProxyProtocol, src: SocketAddr, dst: SocketAddr) -> Vec<u8> {
+    match version {
+        ProxyProtocol::V1 => {
+            let header = match (src, dst) {
+                (SocketAddr::V4(src), SocketAddr::V4(dst)) => format!(
+                    "PROXY TCP4 {} {} {} {}\r\n",
+                    src.ip(),
+                    dst.ip(),
+                    src.port(),
+                    dst.port()
+                ),
+                (SocketAddr::V6(src), SocketAddr::V6(dst)) => format!(
+                    "PROXY TCP6 {} {} {} {}\r\n",
+                    src.ip(),
+                    dst.ip(),
+                    src.port(),
+                    dst.port()
+                ),
+                _ => "PROXY UNKNOWN\r\n".to_owned(),
+            };
+
+            header.into_bytes()
+        }
+        ProxyProtocol::V2 => {
+            let mut header = V2_SIGNATURE.to_vec();
+
+            match (src, dst) {
+                (SocketAddr::V4(src), SocketAddr::V4(dst)) => {
+                    // Version 2, PROXY command, TCP over IPv4
+                    header.extend_from_slice(&[0x21, 0x11]);
+                    header.extend_from_slice(&12_u16.to_be_bytes());
+                    header.extend_from_slice(&src.ip().octets());
+                    header.extend_from_slice(&dst.ip().octets());
+                }
+                (SocketAddr::V6(src), SocketAddr::V6(dst)) => {
+                    // Version 2, PROXY command, TCP over IPv6
+                    header.extend_from_slice(&[0x21, 0x21]);
+                    header.extend_from_slice(&36_u16.to_be_bytes());
+                    header.extend_from_slice(&src.ip().octets());
+                    header.extend_from_slice(&dst.ip().octets());
+                }
+                _ => {
+                    // Version 2, LOCAL command, unspecified family
+                    header.extend_from_slice(&[0x20, 0x00]);
+                    header.extend_from_slice(&0_u16.to_be_bytes());
+                    return header;
+                }
+            }
+
+            header.extend_from_slice(&src.port().to_be_bytes());
+            header.extend_from_slice(&dst.port().to_be_bytes());
+
+            header
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_header() {
+        assert_eq!(
+            header(
+                ProxyProtocol::V1,
+                "192.0.2.1:56324".parse().unwrap(),
+                "198.51.100.2:443".parse().unwrap(),
+            ),
+            b"PROXY TCP4 192.0.2.1 198.51.100.2 56324 443\r\n"
+        );
+
+        assert_eq!(
+            header(
+                ProxyProtocol::V1,
+                "[2001:db8::1]:56324".parse().unwrap(),
+                "[::1]:80".parse().unwrap(),
+            ),
+            b"PROXY TCP6 2001:db8::1 ::1 56324 80\r\n"
+        );
+    }
+
+    #[test]
+    fn v2_header() {
+        let header = header(
+            ProxyProtocol::V2,
+            "192.0.2.1:56324".parse().unwrap(),
+            "198.51.100.2:443".parse().unwrap(),
+        );
+
+        assert_eq!(&header[..12], V2_SIGNATURE);
+        assert_eq!(
+            &header[12..],
+            [0x21, 0x11, 0, 12, 192, 0, 2, 1, 198, 51, 100, 2, 0xdc, 0x04, 0x01, 0xbb]
+        );
+    }
+
+    #[test]
+    fn mixed_families() {
+        let src = "192.0.2.1:56324".parse().unwrap();
+        let dst = "[::1]:80".parse().unwrap();
+
+        assert_eq!(header(ProxyProtocol::V1, src, dst), b"PROXY UNKNOWN\r\n");
+        assert_eq!(
+            header(ProxyProtocol::V2, src, dst)[12..],
+            [0x20, 0x00, 0, 0]
+        );
+    }
+}
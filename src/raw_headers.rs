@@ -0,0 +1,68 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use httparse::Header;
+
+/// Header lines of a response as received, i.e. in wire order and with their original casing.
+///
+/// Added as an extension to responses if [`crate::Options::preserve_raw_headers`] is set.
+///
+/// ```no_run
+/// # use zeptohttpc::{http::Request, Options, RawHeaders, RequestBuilderExt, RequestExt};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut opts = Options::default();
+/// opts.preserve_raw_headers = true;
+///
+/// let resp = Request::get("http://httpbin.org/get").empty()?.send_with_opts(opts)?;
+///
+/// for (name, value) in resp.extensions().get::<RawHeaders>().unwrap().iter() {
+///     println!("{}: {}", name, String::from_utf8_lossy(value));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawHeaders(Vec<(String, Vec<u8>)>);
+
+impl RawHeaders {
+    pub(crate) fn new(headers: &[Header]) -> Self {
+        Self(
+            headers
+                .iter()
+                .map(|header| (header.name.to_owned(), header.value.to_owned()))
+                .collect(),
+        )
+    }
+
+    /// Iterates over all header lines in the order they were received.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_slice()))
+    }
+
+    /// Iterates over the values of all header lines whose name matches ignoring case.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a [u8]> {
+        self.iter()
+            .filter(move |(name1, _value)| name1.eq_ignore_ascii_case(name))
+            .map(|(_name, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
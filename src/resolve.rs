@@ -0,0 +1,187 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use http::uri::Uri;
+
+use super::Error;
+
+/// URI of the request which produced a response, stored as an extension of the response.
+#[derive(Clone)]
+pub struct RequestUri(pub Uri);
+
+/// Resolves a URI reference, e.g. from a `Location` header, against the given base URI as specified by RFC 3986.
+///
+/// Any fragment is stripped as it is not part of the request target and cannot be represented by [`Uri`].
+pub fn resolve(base: &Uri, reference: &str) -> Result<Uri, Error> {
+    let reference = reference
+        .split_once('#')
+        .map_or(reference, |(reference, _fragment)| reference);
+
+    if has_scheme(reference) {
+        let uri = reference.parse::<Uri>()?;
+
+        return match uri.path_and_query() {
+            Some(path_and_query) => {
+                let mut parts = uri.clone().into_parts();
+                parts.path_and_query = Some(
+                    with_query(
+                        &remove_dot_segments(path_and_query.path()),
+                        path_and_query.query(),
+                    )
+                    .parse()?,
+                );
+                Ok(Uri::from_parts(parts)?)
+            }
+            None => Ok(uri),
+        };
+    }
+
+    let scheme = base.scheme_str().unwrap_or("http");
+
+    if let Some(reference) = reference.strip_prefix("//") {
+        return Ok(format!("{}://{}", scheme, reference).parse()?);
+    }
+
+    let authority = base.authority().ok_or(Error::MissingAuthority)?;
+
+    let (path, query) = match reference.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (reference, None),
+    };
+
+    let (path, query) = if path.is_empty() {
+        (base.path().to_owned(), query.or_else(|| base.query()))
+    } else if path.starts_with('/') {
+        (remove_dot_segments(path), query)
+    } else {
+        let base_path = base.path();
+        let dir = &base_path[..base_path.rfind('/').map_or(0, |pos| pos + 1)];
+
+        let merged = if dir.is_empty() {
+            format!("/{}", path)
+        } else {
+            format!("{}{}", dir, path)
+        };
+
+        (remove_dot_segments(&merged), query)
+    };
+
+    Ok(format!("{}://{}{}", scheme, authority, with_query(&path, query)).parse()?)
+}
+
+fn has_scheme(reference: &str) -> bool {
+    match reference.split_once(':') {
+        Some((scheme, _)) => {
+            let mut chars = scheme.chars();
+
+            chars
+                .next()
+                .map_or(false, |char| char.is_ascii_alphabetic())
+                && chars.all(|char| char.is_ascii_alphanumeric() || "+-.".contains(char))
+        }
+        None => false,
+    }
+}
+
+fn with_query(path: &str, query: Option<&str>) -> String {
+    match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_owned(),
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut output = Vec::<&str>::new();
+
+    let mut segments = path.split('/').peekable();
+
+    // The path starts with a slash, so the first segment is empty.
+    if path.starts_with('/') {
+        segments.next();
+    }
+
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+
+        match segment {
+            "." => {
+                if last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                output.pop();
+
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+
+    format!("/{}", output.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_reference_examples() {
+        let base = "http://a/b/c/d;p?q".parse().unwrap();
+
+        for (reference, target) in [
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            (";x", "http://a/b/c/;x"),
+            ("g;x", "http://a/b/c/g;x"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("http://x/y/../z", "http://x/z"),
+            ("#s", "http://a/b/c/d;p?q"),
+            ("g#s", "http://a/b/c/g"),
+            ("g?y#s", "http://a/b/c/g?y"),
+            ("g#s/../x", "http://a/b/c/g"),
+            ("http://x/y#s?z", "http://x/y"),
+        ] {
+            assert_eq!(
+                resolve(&base, reference).unwrap(),
+                target,
+                "reference {:?}",
+                reference
+            );
+        }
+    }
+}
@@ -13,16 +13,35 @@
 // limitations under the License.
 #[cfg(feature = "rustls")]
 use std::convert::TryFrom;
-#[cfg(feature = "rustls")]
-use std::io::ErrorKind::{UnexpectedEof, WouldBlock};
-use std::io::{Read, Result as IoResult, Write};
 #[cfg(any(feature = "native-tls", feature = "rustls"))]
+use std::io::BufReader;
+#[cfg(feature = "rustls")]
+use std::io::ErrorKind::{TimedOut, UnexpectedEof, WouldBlock};
+use std::io::{
+    Cursor, Error as IoError,
+    ErrorKind::{Interrupted, Other},
+    Read, Result as IoResult, Write,
+};
+use std::mem::replace;
 use std::net::TcpStream;
 #[cfg(feature = "rustls")]
-use std::sync::Arc;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use std::time::Instant;
 
 #[cfg(any(feature = "native-tls", feature = "rustls"))]
-use http::uri::Scheme;
+use http::{uri::Scheme, StatusCode};
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use httparse::{
+    Response as ResponseParser,
+    Status::{Complete, Partial},
+    EMPTY_HEADER,
+};
 #[cfg(feature = "native-tls")]
 use native_tls::{HandshakeError, TlsConnector, TlsStream};
 #[cfg(any(feature = "tls-webpki-roots", feature = "tls-native-roots"))]
@@ -36,135 +55,348 @@ use rustls_native_certs::load_native_certs;
 #[cfg(feature = "tls-webpki-roots")]
 use webpki_roots::TLS_SERVER_ROOTS;
 
-use super::{happy_eyeballs::connect, timeout::Timeout, Error, Options};
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+use super::TlsBackend;
+use super::{
+    happy_eyeballs::connect, proxy_protocol::write_header, timeout::Timeout, Error, Options,
+};
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use super::{parse::parse, MAX_HEADERS};
+
+pub struct Stream {
+    inner: Box<dyn Inner>,
+    /// Handle to the underlying socket used to adjust its read timeout and to restart its timeout.
+    socket: Option<TcpStream>,
+    timeout: Option<Timeout>,
+    /// Hands the connection back for reuse when dropped if the flag was set, i.e. the response was read completely.
+    release: Option<(Arc<AtomicBool>, Release)>,
+}
 
-pub struct Stream(Box<dyn Inner>);
+type Release = Box<dyn FnOnce(Stream) + Send>;
 
 trait Inner: Read + Write + Send {}
 
 impl<S> Inner for S where S: Read + Write + Send {}
 
+// Interrupted reads and writes are retried as this is a blocking client.
 impl Read for Stream {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
-        self.0.read(buf)
+        let inner = &mut self.inner;
+
+        match &mut self.timeout {
+            Some(timeout) => retry_interrupted(|| timeout.read(inner, buf)),
+            None => retry_interrupted(|| inner.read(buf)),
+        }
     }
 }
 
 impl Write for Stream {
     fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
-        self.0.write(buf)
+        let written = retry_interrupted(|| self.inner.write(buf))?;
+
+        if written != 0 {
+            if let Some(timeout) = &self.timeout {
+                timeout.touch();
+            }
+        }
+
+        Ok(written)
     }
 
     fn flush(&mut self) -> IoResult<()> {
-        self.0.flush()
+        retry_interrupted(|| self.inner.flush())
+    }
+}
+
+pub fn retry_interrupted<T, F>(mut f: F) -> IoResult<T>
+where
+    F: FnMut() -> IoResult<T>,
+{
+    loop {
+        match f() {
+            Err(err) if err.kind() == Interrupted => continue,
+            res => return res,
+        }
     }
 }
 
 impl Stream {
+    pub fn from_io<S>(stream: S) -> Self
+    where
+        S: Read + Write + Send + 'static,
+    {
+        Self {
+            inner: Box::new(stream),
+            socket: None,
+            timeout: None,
+            release: None,
+        }
+    }
+
     pub fn new(
         #[cfg(any(feature = "native-tls", feature = "rustls"))] scheme: &Scheme,
         host: &str,
         port: u16,
+        proxy: Option<(&str, u16)>,
         opts: &Options,
     ) -> Result<Self, Error> {
-        let stream = connect(host, port, opts)?;
+        let stream = match proxy {
+            Some((proxy_host, proxy_port)) => connect(proxy_host, proxy_port, opts)?,
+            None => connect(host, port, opts)?,
+        };
+
+        if let Some(configure_socket) = opts.configure_socket {
+            configure_socket(&stream)?;
+        }
+
+        if let Some(version) = opts.proxy_protocol {
+            write_header(&stream, version)?;
+        }
 
-        let inner: Box<dyn Inner> = match opts.deadline {
+        let socket = match (opts.first_byte_timeout, opts.deadline, opts.idle_timeout) {
+            (None, None, None) => None,
+            _ => Some(stream.try_clone()?),
+        };
+
+        let timeout = start_timeout(&stream, opts)?;
+
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        if proxy.is_some() && scheme == &Scheme::HTTPS {
+            establish_tunnel(&stream, host, port, opts)?;
+        }
+
+        // Limit the bytes received over the connection before they are decrypted.
+        let mut transport: Box<dyn Inner> = Box::new(stream);
+
+        if let Some(max_wire_bytes) = opts.max_wire_bytes {
+            transport = Box::new(WireLimit {
+                inner: transport,
+                rem: max_wire_bytes,
+            });
+        }
+
+        let inner: Box<dyn Inner> = match () {
             #[cfg(feature = "native-tls")]
-            None if scheme == &Scheme::HTTPS => {
-                let stream = perform_native_tls_handshake(stream, host, opts.tls_connector)?;
+            () if scheme == &Scheme::HTTPS && uses_native_tls(opts) => {
+                let stream = perform_native_tls_handshake(
+                    transport,
+                    host,
+                    opts.tls_connector,
+                    opts.send_sni,
+                    opts.deadline,
+                )?;
 
                 Box::new(stream)
             }
             #[cfg(feature = "rustls")]
-            None if scheme == &Scheme::HTTPS => {
-                let stream = perform_rustls_handshake(stream, host, opts.client_config)?;
+            () if scheme == &Scheme::HTTPS => {
+                let stream = perform_rustls_handshake(
+                    transport,
+                    host,
+                    opts.client_config,
+                    opts.send_sni,
+                    opts.deadline,
+                )?;
 
                 Box::new(HandleCloseNotify(stream))
             }
-            None => Box::new(stream),
-            #[cfg(feature = "native-tls")]
-            Some(deadline) if scheme == &Scheme::HTTPS => {
-                let timeout = Timeout::start(&stream, deadline)?;
-                let stream = perform_native_tls_handshake(stream, host, opts.tls_connector)?;
+            () => transport,
+        };
 
-                Box::new(WithTimeout(stream, timeout))
-            }
-            #[cfg(feature = "rustls")]
-            Some(deadline) if scheme == &Scheme::HTTPS => {
-                let timeout = Timeout::start(&stream, deadline)?;
-                let stream = perform_rustls_handshake(stream, host, opts.client_config)?;
+        Ok(Self {
+            inner,
+            socket,
+            timeout,
+            release: None,
+        })
+    }
 
-                Box::new(WithTimeout(HandleCloseNotify(stream), timeout))
-            }
-            Some(deadline) => {
-                let timeout = Timeout::start(&stream, deadline)?;
+    /// Replaces the timeout of the previous request by one based on the given options, e.g. when reusing the connection.
+    pub fn restart_timeout(&mut self, opts: &Options) -> Result<(), Error> {
+        // Dropping the timeout stops it without shutting down the stream.
+        self.timeout = None;
+
+        if let Some(socket) = &self.socket {
+            self.timeout = start_timeout(socket, opts)?;
+        }
+
+        Ok(())
+    }
+
+    /// Calls `release` with the connection when the stream is dropped after `released` was set.
+    pub fn release_to(&mut self, released: Arc<AtomicBool>, release: Release) {
+        self.release = Some((released, release));
+    }
+
+    pub fn read_timeout(&self) -> IoResult<Option<Duration>> {
+        match &self.socket {
+            Some(socket) => socket.read_timeout(),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match &self.socket {
+            Some(socket) => socket.set_read_timeout(timeout),
+            None => Ok(()),
+        }
+    }
+}
 
-                Box::new(WithTimeout(stream, timeout))
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if let Some((released, release)) = self.release.take() {
+            if released.load(Ordering::Acquire) {
+                // The timeout of this request must not shut down the connection while it is idle.
+                self.timeout = None;
+
+                release(Self {
+                    inner: replace(&mut self.inner, Box::new(Cursor::new(Vec::new()))),
+                    socket: self.socket.take(),
+                    timeout: None,
+                    release: None,
+                });
             }
-        };
+        }
+    }
+}
+
+fn start_timeout(stream: &TcpStream, opts: &Options) -> Result<Option<Timeout>, Error> {
+    match (opts.deadline, opts.idle_timeout) {
+        (None, None) => Ok(None),
+        (deadline, idle_timeout) => Timeout::start(stream, deadline, idle_timeout).map(Some),
+    }
+}
 
-        Ok(Self(inner))
+/// Whether `native-tls` is used instead of `rustls` which is only a choice if both are enabled.
+#[cfg(feature = "native-tls")]
+fn uses_native_tls(_opts: &Options) -> bool {
+    #[cfg(feature = "rustls")]
+    {
+        _opts.tls_backend == TlsBackend::NativeTls
+    }
+
+    #[cfg(not(feature = "rustls"))]
+    {
+        true
     }
 }
 
-struct WithTimeout<S>(S, Timeout);
+/// Fails reading once more than the given number of bytes were received over the connection.
+struct WireLimit<S> {
+    inner: S,
+    rem: u64,
+}
 
-impl<S> Read for WithTimeout<S>
+impl<S> Read for WireLimit<S>
 where
     S: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
-        self.1.read(&mut self.0, buf)
+        let read = self.inner.read(buf)?;
+
+        self.rem = self
+            .rem
+            .checked_sub(read as u64)
+            .ok_or_else(|| IoError::new(Other, Error::WireLimitExceeded))?;
+
+        Ok(read)
     }
 }
 
-impl<S> Write for WithTimeout<S>
+impl<S> Write for WireLimit<S>
 where
     S: Write,
 {
     fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
-        self.0.write(buf)
+        self.inner.write(buf)
     }
 
     fn flush(&mut self) -> IoResult<()> {
-        self.0.flush()
+        self.inner.flush()
+    }
+}
+
+/// Asks an HTTP proxy to open a tunnel to the given authority using the `CONNECT` method.
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+fn establish_tunnel(
+    mut stream: &TcpStream,
+    host: &str,
+    port: u16,
+    opts: &Options,
+) -> Result<(), Error> {
+    write!(
+        stream,
+        "CONNECT {host}:{port} HTTP/1.1\r\nhost: {host}:{port}\r\n\r\n",
+        host = host,
+        port = port
+    )?;
+    stream.flush()?;
+
+    // The proxy does not send anything after its response until the TLS handshake starts,
+    // hence no bytes belonging to the tunnel are lost when the reader is dropped.
+    let code = parse(
+        BufReader::new(stream),
+        opts.max_header_bytes,
+        &mut Vec::new(),
+        |buf| -> Result<_, Error> {
+            let mut headers = [EMPTY_HEADER; MAX_HEADERS];
+            let mut parser = ResponseParser::new(&mut headers);
+
+            match parser.parse(buf)? {
+                Complete(parsed) => Ok(Complete((parsed, parser.code))),
+                Partial => Ok(Partial),
+            }
+        },
+    )?;
+
+    let status =
+        StatusCode::from_u16(code.ok_or(Error::MissingStatus)?).map_err(http::Error::from)?;
+
+    if !status.is_success() {
+        return Err(Error::ProxyConnect(status));
     }
+
+    Ok(())
 }
 
 #[cfg(feature = "native-tls")]
 fn perform_native_tls_handshake(
-    stream: TcpStream,
+    stream: Box<dyn Inner>,
     host: &str,
     tls_connector: Option<&TlsConnector>,
-) -> Result<TlsStream<TcpStream>, Error> {
-    let handshake = match tls_connector {
+    send_sni: bool,
+    deadline: Option<Instant>,
+) -> Result<TlsStream<Box<dyn Inner>>, Error> {
+    let mut handshake = match tls_connector {
         Some(tls_connector) => tls_connector.connect(host, stream),
-        None => TlsConnector::new()?.connect(host, stream),
+        None => TlsConnector::builder()
+            .use_sni(send_sni)
+            .build()?
+            .connect(host, stream),
     };
 
-    match handshake {
-        Ok(stream) => Ok(stream),
-        Err(HandshakeError::Failure(err)) => Err(err.into()),
-        Err(HandshakeError::WouldBlock(mut stream)) => loop {
-            match stream.handshake() {
-                Ok(stream) => return Ok(stream),
-                Err(HandshakeError::Failure(err)) => return Err(err.into()),
-                Err(HandshakeError::WouldBlock(stream1)) => stream = stream1,
-            }
-        },
+    loop {
+        match handshake {
+            Ok(stream) => return Ok(stream),
+            Err(_) if deadline_passed(deadline) => return Err(Error::TlsHandshakeTimeout),
+            Err(HandshakeError::Failure(err)) => return Err(err.into()),
+            Err(HandshakeError::WouldBlock(stream)) => handshake = stream.handshake(),
+        }
     }
 }
 
 #[cfg(feature = "rustls")]
 fn perform_rustls_handshake(
-    mut stream: TcpStream,
+    mut stream: Box<dyn Inner>,
     host: &str,
     client_config: Option<&Arc<ClientConfig>>,
-) -> Result<StreamOwned<ClientConnection, TcpStream>, Error> {
-    let name = ServerName::try_from(host).map_err(|_| Error::InvalidServerName(host.to_owned()))?;
+    send_sni: bool,
+    deadline: Option<Instant>,
+) -> Result<StreamOwned<ClientConnection, Box<dyn Inner>>, Error> {
+    let name = server_name(host)?;
 
-    let client_config = match client_config {
+    let mut client_config = match client_config {
         Some(client_config) => client_config.clone(),
         #[cfg(any(feature = "tls-webpki-roots", feature = "tls-native-roots"))]
         None => {
@@ -192,10 +424,25 @@ fn perform_rustls_handshake(
         None => return Err(Error::MissingTlsRoots),
     };
 
-    let mut conn = ClientConnection::new(client_config, name.to_owned())?;
+    if !send_sni && client_config.enable_sni {
+        let mut client_config1 = ClientConfig::clone(&client_config);
+        client_config1.enable_sni = false;
+        client_config = Arc::new(client_config1);
+    }
+
+    let mut conn = ClientConnection::new(client_config, name)?;
 
+    // Read timeouts surface as `WouldBlock` or `TimedOut` and are retried until the deadline.
     while let Err(err) = conn.complete_io(&mut stream) {
-        if err.kind() != WouldBlock || !conn.is_handshaking() {
+        if !conn.is_handshaking() {
+            return Err(err.into());
+        }
+
+        if deadline_passed(deadline) {
+            return Err(Error::TlsHandshakeTimeout);
+        }
+
+        if err.kind() != WouldBlock && err.kind() != TimedOut {
             return Err(err.into());
         }
     }
@@ -203,23 +450,44 @@ fn perform_rustls_handshake(
     Ok(StreamOwned::new(conn, stream))
 }
 
+/// A handshake failing after the deadline passed was most likely aborted by the timeout.
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+fn deadline_passed(deadline: Option<Instant>) -> bool {
+    deadline.map_or(false, |deadline| Instant::now() >= deadline)
+}
+
+#[cfg(feature = "rustls")]
+fn server_name(host: &str) -> Result<ServerName<'static>, Error> {
+    let name = match host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        Some(addr) => addr
+            .parse::<Ipv6Addr>()
+            .map(|addr| ServerName::IpAddress(IpAddr::V6(addr).into()))
+            .ok(),
+        None => ServerName::try_from(host).map(|name| name.to_owned()).ok(),
+    };
+
+    name.ok_or_else(|| Error::InvalidServerName(host.to_owned()))
+}
+
 #[cfg(feature = "rustls")]
-struct HandleCloseNotify(StreamOwned<ClientConnection, TcpStream>);
+struct HandleCloseNotify(StreamOwned<ClientConnection, Box<dyn Inner>>);
 
 #[cfg(feature = "rustls")]
 impl Read for HandleCloseNotify {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         let res = self.0.read(buf);
 
-        match res {
-            Err(err) if err.kind() == UnexpectedEof => {
+        if let Err(err) = &res {
+            if err.kind() == UnexpectedEof {
                 self.0.conn.send_close_notify();
-                self.0.conn.complete_io(&mut self.0.sock)?;
-
-                Ok(0)
+                let _ = self.0.conn.complete_io(&mut self.0.sock);
             }
-            res => res,
         }
+
+        res
     }
 }
 
@@ -233,3 +501,33 @@ impl Write for HandleCloseNotify {
         self.0.flush()
     }
 }
+
+#[cfg(all(test, feature = "rustls"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_name_of_ipv6_address() {
+        let name = server_name("[::1]").unwrap();
+
+        assert_eq!(
+            name,
+            ServerName::IpAddress(IpAddr::from(Ipv6Addr::LOCALHOST).into())
+        );
+    }
+
+    #[test]
+    fn server_name_of_domain() {
+        let name = server_name("localhost").unwrap();
+
+        assert_eq!(name, ServerName::try_from("localhost").unwrap());
+    }
+
+    #[test]
+    fn invalid_server_name() {
+        match server_name("[localhost]") {
+            Err(Error::InvalidServerName(name)) => assert_eq!(name, "[localhost]"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+}
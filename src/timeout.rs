@@ -13,42 +13,93 @@
 // limitations under the License.
 use std::io::{ErrorKind::TimedOut, Read, Result as IoResult};
 use std::net::{Shutdown, TcpStream};
-use std::sync::mpsc::{channel, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{
+    mpsc::{channel, RecvTimeoutError, Sender, TryRecvError},
+    Arc, Mutex,
+};
 use std::thread::spawn;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use super::Error;
 
-pub struct Timeout(Sender<()>);
+/// Shuts down the stream once the deadline passed or no data was transferred for the idle timeout.
+pub struct Timeout {
+    tx: Option<Sender<()>>,
+    expired: bool,
+    activity: Option<Arc<Mutex<Instant>>>,
+}
 
 impl Timeout {
-    pub fn start(stream: &TcpStream, deadline: Instant) -> Result<Self, Error> {
+    pub fn start(
+        stream: &TcpStream,
+        deadline: Option<Instant>,
+        idle_timeout: Option<Duration>,
+    ) -> Result<Self, Error> {
         let stream = stream.try_clone()?;
         let (tx, rx) = channel();
 
-        spawn(move || {
-            let shutdown = match deadline.checked_duration_since(Instant::now()) {
-                Some(timeout) => rx.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout),
-                None => rx.try_recv() == Err(TryRecvError::Empty),
-            };
+        let activity = idle_timeout.map(|_| Arc::new(Mutex::new(Instant::now())));
+
+        let idle = activity.clone().zip(idle_timeout);
+
+        spawn(move || loop {
+            let mut expires = deadline;
+
+            // The idle timeout is re-armed by moving its expiry based on the last activity.
+            if let Some((activity, idle_timeout)) = &idle {
+                let idle_expires = *activity.lock().unwrap() + *idle_timeout;
+
+                expires = Some(expires.map_or(idle_expires, |expires| expires.min(idle_expires)));
+            }
+
+            let shutdown =
+                match expires.and_then(|expires| expires.checked_duration_since(Instant::now())) {
+                    Some(timeout) if timeout != Duration::ZERO => match rx.recv_timeout(timeout) {
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        _ => false,
+                    },
+                    _ => rx.try_recv() == Err(TryRecvError::Empty),
+                };
 
             if shutdown {
                 drop(rx);
 
                 let _ = stream.shutdown(Shutdown::Both);
             }
+
+            return;
         });
 
-        Ok(Self(tx))
+        Ok(Self {
+            tx: Some(tx),
+            expired: false,
+            activity,
+        })
     }
 
-    pub fn read<R: Read>(&self, reader: &mut R, buf: &mut [u8]) -> IoResult<usize> {
+    pub fn read<R: Read>(&mut self, reader: &mut R, buf: &mut [u8]) -> IoResult<usize> {
         let read = reader.read(buf)?;
 
-        if read == 0 && !buf.is_empty() && self.0.send(()).is_err() {
-            return Err(TimedOut.into());
+        if read != 0 {
+            self.touch();
+        } else if !buf.is_empty() {
+            // Stop the timer only once as reading might be retried after EOF.
+            if let Some(tx) = self.tx.take() {
+                self.expired = tx.send(()).is_err();
+            }
+
+            if self.expired {
+                return Err(TimedOut.into());
+            }
         }
 
         Ok(read)
     }
+
+    /// Re-arms the idle timeout, if any, after data was transferred.
+    pub fn touch(&self) {
+        if let Some(activity) = &self.activity {
+            *activity.lock().unwrap() = Instant::now();
+        }
+    }
 }
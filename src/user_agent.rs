@@ -0,0 +1,141 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use http::header::HeaderValue;
+
+use super::Error;
+
+/// Builds a `User-Agent` header value from product tokens and comments as specified by RFC 7231.
+///
+/// ```
+/// # use zeptohttpc::UserAgent;
+/// let user_agent = UserAgent::new()
+///     .product("MyBot", Some("1.0"))
+///     .unwrap()
+///     .comment("+https://example.com/bot")
+///     .unwrap()
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(user_agent, "MyBot/1.0 (+https://example.com/bot)");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UserAgent(String);
+
+impl UserAgent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a product token with an optional version.
+    pub fn product(mut self, name: &str, version: Option<&str>) -> Result<Self, Error> {
+        if !is_token(name) || !version.map_or(true, is_token) {
+            return Err(Error::InvalidUserAgent);
+        }
+
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+
+        self.0.push_str(name);
+
+        if let Some(version) = version {
+            self.0.push('/');
+            self.0.push_str(version);
+        }
+
+        Ok(self)
+    }
+
+    /// Appends a comment after at least one product, escaping parentheses and backslashes.
+    pub fn comment(mut self, comment: &str) -> Result<Self, Error> {
+        if self.0.is_empty() {
+            return Err(Error::InvalidUserAgent);
+        }
+
+        self.0.push_str(" (");
+
+        for char_ in comment.chars() {
+            match char_ {
+                '(' | ')' | '\\' => {
+                    self.0.push('\\');
+                    self.0.push(char_);
+                }
+                '\t' | ' '..='~' => self.0.push(char_),
+                _ => return Err(Error::InvalidUserAgent),
+            }
+        }
+
+        self.0.push(')');
+
+        Ok(self)
+    }
+
+    pub fn build(&self) -> Result<HeaderValue, Error> {
+        if self.0.is_empty() {
+            return Err(Error::InvalidUserAgent);
+        }
+
+        HeaderValue::from_str(&self.0).map_err(Error::from)
+    }
+}
+
+fn is_token(token: &str) -> bool {
+    !token.is_empty()
+        && token
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_products_and_comments() {
+        let user_agent = UserAgent::new()
+            .product("foo", Some("1.0"))
+            .unwrap()
+            .product("bar", None)
+            .unwrap()
+            .comment("baz (qux)")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(user_agent, "foo/1.0 bar (baz \\(qux\\))");
+    }
+
+    #[test]
+    fn reject_invalid_tokens() {
+        UserAgent::new().product("foo bar", None).unwrap_err();
+        UserAgent::new().product("foo", Some("1/0")).unwrap_err();
+        UserAgent::new().product("", None).unwrap_err();
+    }
+
+    #[test]
+    fn reject_invalid_comments() {
+        UserAgent::new().comment("foo").unwrap_err();
+
+        UserAgent::new()
+            .product("foo", None)
+            .unwrap()
+            .comment("bar\r\nbaz")
+            .unwrap_err();
+    }
+
+    #[test]
+    fn reject_empty() {
+        UserAgent::new().build().unwrap_err();
+    }
+}
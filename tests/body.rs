@@ -0,0 +1,732 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![allow(clippy::field_reassign_with_default)]
+
+mod common;
+
+use std::env::temp_dir;
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all};
+use std::io::ErrorKind;
+use std::process;
+
+use zeptohttpc::{
+    http::{Request, StatusCode, Version},
+    Error, Options, RequestBuilderExt, RequestExt, ResponseExt,
+};
+
+use common::{MockServer, MockStream};
+
+#[test]
+fn reads_body_of_exact_content_length() {
+    let mock = MockServer::start(vec!["HTTP/1.0 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar"]);
+
+    let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+
+    let body = resp.into_vec().unwrap();
+    assert_eq!(b"foobar", &body[..]);
+    assert_eq!(6, body.capacity());
+}
+
+#[test]
+fn fails_if_body_is_shorter_than_content_length() {
+    let mock = MockServer::start(vec!["HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nfoobar"]);
+
+    let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+
+    let err = resp.into_vec().unwrap_err();
+    assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+}
+
+#[test]
+fn does_not_preallocate_huge_content_length() {
+    let head = "HTTP/1.1 200 Ok\r\nContent-Length: 1000000000000\r\n\r\nfoobar";
+
+    let (stream, _req) = MockStream::new(head);
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    let err = resp.into_vec().unwrap_err();
+    assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+
+    #[cfg(feature = "bytes")]
+    {
+        let (stream, _req) = MockStream::new(head);
+
+        let resp = Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, Default::default())
+            .unwrap();
+
+        let err = resp.into_bytes().unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+    }
+}
+
+#[test]
+fn fails_if_body_is_longer_than_content_length() {
+    let mock = MockServer::start(vec!["HTTP/1.0 200 Ok\r\nContent-Length: 3\r\n\r\nfoobar"]);
+
+    let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+
+    resp.into_vec().unwrap_err();
+}
+
+#[test]
+fn fails_if_too_many_bytes_are_received() {
+    let send = |max_wire_bytes| {
+        let mock = MockServer::start(vec!["HTTP/1.0 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar"]);
+
+        let mut opts = Options::default();
+        opts.max_wire_bytes = Some(max_wire_bytes);
+
+        let resp = Request::get(mock.uri())
+            .empty()
+            .unwrap()
+            .send_with_opts(opts);
+
+        match resp {
+            Ok(resp) => resp.into_string(),
+            Err(Error::Io(err)) => Err(err),
+            Err(err) => panic!("Unexpected error: {}", err),
+        }
+    };
+
+    assert_eq!("foobar", send(44).unwrap());
+
+    let err = send(43).unwrap_err();
+    assert!(matches!(
+        err.into_inner().unwrap().downcast::<Error>().as_deref(),
+        Ok(Error::WireLimitExceeded)
+    ));
+}
+
+#[test]
+fn fails_if_response_is_larger_than_limit() {
+    let mut resp = b"HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for _ in 0..16 {
+        resp.extend_from_slice(b"40\r\n");
+        resp.extend_from_slice(&[b'x'; 64]);
+        resp.extend_from_slice(b"\r\n");
+    }
+    resp.extend_from_slice(b"0\r\n\r\n");
+
+    let mock = MockServer::start_bytes(vec![resp]);
+
+    let mut opts = Options::default();
+    opts.max_response_size = Some(512);
+
+    let resp = Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let err = resp.into_vec().unwrap_err();
+    assert!(matches!(
+        err.into_inner().unwrap().downcast::<Error>().as_deref(),
+        Ok(Error::BodyTooLarge)
+    ));
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn reads_body_into_bytes() {
+    for resp in [
+        "HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar",
+        "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n",
+    ] {
+        let (stream, _req) = MockStream::new(resp);
+
+        let resp = Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, Default::default())
+            .unwrap();
+
+        assert_eq!(&b"foobar"[..], resp.into_bytes().unwrap());
+    }
+}
+
+#[test]
+fn downloads_body_to_file() {
+    let dir = temp_dir().join(format!("zeptohttpc-download-{}", process::id()));
+    create_dir_all(&dir).unwrap();
+    let path = dir.join("foobar.txt");
+
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nbarfoo",
+    ]);
+
+    let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+    assert_eq!(6, resp.download_to(&path).unwrap());
+    assert_eq!("foobar", read_to_string(&path).unwrap());
+
+    let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+    resp.download_to(&path).unwrap_err();
+    assert_eq!("foobar", read_to_string(&path).unwrap());
+
+    assert_eq!(1, read_dir(&dir).unwrap().count());
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reads_body_buffered_with_headers() {
+    for read_buffer_size in [8 * 1024, 16] {
+        let (stream, _req) = MockStream::new("HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar");
+
+        let mut opts = Options::default();
+        opts.read_buffer_size = read_buffer_size;
+
+        let resp = Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, opts)
+            .unwrap();
+
+        assert_eq!(b"foobar", &resp.into_vec().unwrap()[..]);
+    }
+}
+
+#[test]
+fn reads_chunked_body_buffered_with_headers() {
+    for read_buffer_size in [8 * 1024, 16] {
+        let (stream, _req) = MockStream::new(
+            "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n",
+        );
+
+        let mut opts = Options::default();
+        opts.read_buffer_size = read_buffer_size;
+
+        let resp = Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, opts)
+            .unwrap();
+
+        assert_eq!(b"foobar", &resp.into_vec().unwrap()[..]);
+    }
+}
+
+#[test]
+fn reads_http_0_9_response() {
+    let (stream, _req) = MockStream::new("<html>foobar</html>");
+
+    let mut opts = Options::default();
+    opts.allow_http_0_9 = true;
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts)
+        .unwrap();
+
+    assert_eq!(Version::HTTP_09, resp.version());
+    assert_eq!(200, resp.status().as_u16());
+    assert_eq!("<html>foobar</html>", resp.into_string().unwrap());
+}
+
+#[test]
+fn reads_http_1_x_response_if_http_0_9_is_allowed() {
+    let (stream, _req) =
+        MockStream::new("HTTP/1.1 404 Not Found\r\nContent-Length: 6\r\n\r\nfoobar");
+
+    let mut opts = Options::default();
+    opts.allow_http_0_9 = true;
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts)
+        .unwrap();
+
+    assert_eq!(Version::HTTP_11, resp.version());
+    assert_eq!(404, resp.status().as_u16());
+    assert_eq!("foobar", resp.into_string().unwrap());
+}
+
+#[test]
+fn fails_if_content_length_exceeds_limit() {
+    let mock = MockServer::start(vec!["HTTP/1.0 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar"]);
+
+    let mut opts = Options::default();
+    opts.max_content_length = Some(5);
+
+    let res = Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::BodyTooLarge) => (),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn ignores_content_length_for_head_requests() {
+    let mock = MockServer::start(vec!["HTTP/1.0 200 Ok\r\nContent-Length: 6\r\n\r\n"]);
+
+    let resp = Request::head(mock.uri()).empty().unwrap().send().unwrap();
+
+    let body = resp.into_vec().unwrap();
+    assert!(body.is_empty());
+}
+
+#[test]
+fn ignores_body_of_no_content_response() {
+    let (stream, _req) =
+        MockStream::new("HTTP/1.1 204 No Content\r\nContent-Length: 6\r\n\r\nfoobar");
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    let body = resp.into_vec().unwrap();
+    assert!(body.is_empty());
+}
+
+#[test]
+fn rejects_body_of_not_modified_response_if_strict() {
+    let (stream, _req) = MockStream::new("HTTP/1.1 304 Not Modified\r\n\r\nfoobar");
+
+    let mut opts = Options::default();
+    opts.strict_bodyless = true;
+
+    let res = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts);
+
+    match res {
+        Err(Error::UnexpectedBody) => (),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn accepts_content_length_of_head_response_if_strict() {
+    let (stream, _req) = MockStream::new("HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\n");
+
+    let mut opts = Options::default();
+    opts.strict_bodyless = true;
+
+    let resp = Request::head("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts)
+        .unwrap();
+
+    let body = resp.into_vec().unwrap();
+    assert!(body.is_empty());
+}
+
+#[cfg(all(feature = "flate2", feature = "json"))]
+#[test]
+fn reads_compressed_chunked_json() {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use zeptohttpc::serde_json::{json, Value};
+
+    let mut encoder = GzEncoder::new(Vec::new(), Default::default());
+    encoder
+        .write_all(br#"{"foo":"bar","baz":[1,2,3]}"#)
+        .unwrap();
+    let body = encoder.finish().unwrap();
+
+    let mut resp = b"HTTP/1.1 200 Ok\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for chunk in body.chunks(7) {
+        write!(resp, "{:x}\r\n", chunk.len()).unwrap();
+        resp.extend_from_slice(chunk);
+        resp.extend_from_slice(b"\r\n");
+    }
+    resp.extend_from_slice(b"0\r\n\r\n");
+
+    let mock = MockServer::start_bytes(vec![resp]);
+
+    let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+
+    let body: Value = resp.json().unwrap();
+    assert_eq!(json!({ "foo": "bar", "baz": [1, 2, 3] }), body);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn reports_position_of_json_errors() {
+    use zeptohttpc::serde_json::Value;
+
+    let (stream, _req) = MockStream::new(
+        "HTTP/1.1 200 Ok\r\nContent-Type: application/json\r\nContent-Length: 14\r\n\r\n{\n  \"foo\": ]\n}",
+    );
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    match resp.try_json::<Value>() {
+        Err(Error::Json(err)) => {
+            assert!(err.is_syntax());
+            assert_eq!(2, err.line());
+            assert_eq!(10, err.column());
+        }
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(value) => panic!("Unexpected value: {}", value),
+    }
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn decodes_body_using_given_charset() {
+    // "日本" in Shift_JIS
+    let mut resp =
+        b"HTTP/1.1 200 Ok\r\nContent-Type: text/plain\r\nContent-Length: 4\r\n\r\n".to_vec();
+    resp.extend_from_slice(&[0x93, 0xfa, 0x96, 0x7b]);
+
+    let (stream, _req) = MockStream::new(resp);
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    assert_eq!("日本", resp.into_string_with_charset("Shift_JIS").unwrap());
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn given_charset_overrides_declared_one() {
+    let (stream, _req) = MockStream::new(
+        b"HTTP/1.1 200 Ok\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 2\r\n\r\n\xe4\xf6".to_vec(),
+    );
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    assert_eq!("äö", resp.into_string_with_charset("latin1").unwrap());
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn rejects_unknown_charset() {
+    let (stream, _req) = MockStream::new("HTTP/1.1 200 Ok\r\nContent-Length: 3\r\n\r\nfoo");
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    let err = resp
+        .into_string_with_charset("no-such-charset")
+        .unwrap_err();
+    assert!(matches!(
+        err.into_inner().unwrap().downcast::<Error>().as_deref(),
+        Ok(Error::UnknownCharset)
+    ));
+}
+
+#[test]
+fn reads_unknown_charset_lossily() {
+    let (stream, _req) = MockStream::new(
+        b"HTTP/1.1 200 Ok\r\nContent-Type: text/plain; charset=x-no-such-charset\r\nContent-Length: 5\r\n\r\nfo\xffob".to_vec(),
+    );
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    assert_eq!("fo\u{fffd}ob", resp.into_string().unwrap());
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn decodes_charset_followed_by_other_parameters() {
+    let (stream, _req) = MockStream::new(
+        b"HTTP/1.1 200 Ok\r\nContent-Type: text/plain; charset=latin1; format=flowed\r\nContent-Length: 2\r\n\r\n\xe4\xf6".to_vec(),
+    );
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    assert_eq!("äö", resp.into_string().unwrap());
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn decodes_quoted_charset() {
+    let (stream, _req) = MockStream::new(
+        b"HTTP/1.1 200 Ok\r\nContent-Type: text/plain; charset=\"Shift_JIS\"\r\nContent-Length: 4\r\n\r\n\x93\xfa\x96\x7b".to_vec(),
+    );
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    assert_eq!("日本", resp.into_string().unwrap());
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn fails_on_malformed_text_if_strict() {
+    use std::io::Read;
+
+    let resp = || {
+        MockStream::new(
+            b"HTTP/1.1 200 Ok\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 3\r\n\r\nf\xffo".to_vec(),
+        )
+        .0
+    };
+
+    let mut body = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(resp(), Default::default())
+        .unwrap()
+        .into_body();
+
+    let mut buf = String::new();
+    body.read_to_string(&mut buf).unwrap();
+    assert_eq!("f\u{fffd}o", buf);
+    assert!(body.had_decoding_errors());
+
+    let mut opts = Options::default();
+    opts.strict_decoding = true;
+
+    let err = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(resp(), opts)
+        .unwrap()
+        .into_string()
+        .unwrap_err();
+
+    assert_eq!(ErrorKind::InvalidData, err.kind());
+}
+
+#[cfg(feature = "flate2")]
+#[test]
+fn removes_chunked_framing_before_decompressing() {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+
+    let text = "The quick brown fox jumps over the lazy dog.\r\n".repeat(50);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Default::default());
+    encoder.write_all(text.as_bytes()).unwrap();
+    let body = encoder.finish().unwrap();
+
+    for chunk_size in [1, 5, body.len()] {
+        let mut resp =
+            b"HTTP/1.1 200 Ok\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n"
+                .to_vec();
+        for chunk in body.chunks(chunk_size) {
+            write!(resp, "{:x}\r\n", chunk.len()).unwrap();
+            resp.extend_from_slice(chunk);
+            resp.extend_from_slice(b"\r\n");
+        }
+        resp.extend_from_slice(b"0\r\n\r\n");
+
+        let (stream, _req) = MockStream::new(resp);
+
+        let resp = Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, Default::default())
+            .unwrap();
+
+        assert_eq!(text, resp.into_string().unwrap());
+    }
+}
+
+#[test]
+fn splits_response_into_head_and_body() {
+    let (stream, _req) =
+        MockStream::new("HTTP/1.1 404 Not Found\r\nContent-Length: 6\r\n\r\nfoobar");
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap();
+
+    let (head, mut body) = resp.into_head_and_body();
+    assert_eq!(404, head.status);
+    assert_eq!(Some(6), body.exact_len());
+    assert_eq!(b"foobar", &body.read_exact_vec(6).unwrap()[..]);
+}
+
+#[test]
+fn exposes_trailers_of_chunked_body() {
+    use std::io::Read;
+
+    let (stream, _req) = MockStream::new(
+        "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nfoobar\r\n0\r\nX-Foo: bar\r\n\r\n",
+    );
+
+    let mut body = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Default::default())
+        .unwrap()
+        .into_body();
+
+    assert!(body.trailers().is_none());
+
+    assert_eq!(b"foobar", &body.read_exact_vec(6).unwrap()[..]);
+    assert!(body.trailers().is_none());
+
+    assert_eq!(0, body.read_to_end(&mut Vec::new()).unwrap());
+    assert_eq!(body.trailers().unwrap()["x-foo"], "bar");
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn verifies_checksum_trailer() {
+    let resp = |checksum| {
+        MockStream::new(format!(
+            "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n3\r\nbar\r\n0\r\nX-Checksum-Sha256: {}\r\n\r\n",
+            checksum
+        ))
+        .0
+    };
+
+    let mut opts = Options::default();
+    opts.verify_checksum = true;
+
+    let body = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(
+            resp("C3AB8FF13720E8AD9047DD39466B3C8974E592C2FA383D4A3960714CAEF0C4F2"),
+            opts,
+        )
+        .unwrap()
+        .into_string()
+        .unwrap();
+
+    assert_eq!("foobar", body);
+
+    let err = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(
+            resp("0000000000000000000000000000000000000000000000000000000000000000"),
+            opts,
+        )
+        .unwrap()
+        .into_string()
+        .unwrap_err();
+
+    assert!(matches!(
+        err.into_inner().unwrap().downcast::<Error>().as_deref(),
+        Ok(Error::ChecksumMismatch)
+    ));
+}
+
+#[test]
+fn fails_on_unexpected_status() {
+    let resp = |head: &'static str| {
+        Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(MockStream::new(head).0, Default::default())
+            .unwrap()
+    };
+
+    let allowed = [StatusCode::OK, StatusCode::CREATED];
+
+    let ok = resp("HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n")
+        .expect_status(&allowed)
+        .unwrap();
+    assert_eq!(201, ok.status());
+
+    match resp("HTTP/1.1 202 Accepted\r\nRetry-After: 5\r\nContent-Length: 0\r\n\r\n")
+        .expect_status(&allowed)
+    {
+        Err(Error::UnexpectedStatus {
+            got,
+            allowed: allowed1,
+            headers,
+        }) => {
+            assert_eq!(StatusCode::ACCEPTED, got);
+            assert_eq!(allowed1, allowed);
+            assert_eq!(headers["retry-after"], "5");
+        }
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn reports_whether_body_is_expected() {
+    let has_body = |method: &str, resp: &'static str| {
+        let (stream, _req) = MockStream::new(resp);
+
+        Request::builder()
+            .method(method)
+            .uri("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, Default::default())
+            .unwrap()
+            .has_body()
+    };
+
+    assert!(has_body(
+        "GET",
+        "HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\nfoobar"
+    ));
+    assert!(!has_body(
+        "GET",
+        "HTTP/1.1 200 Ok\r\nContent-Length: 0\r\n\r\n"
+    ));
+    assert!(has_body(
+        "GET",
+        "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n"
+    ));
+    assert!(has_body("GET", "HTTP/1.0 200 Ok\r\n\r\nfoobar"));
+    assert!(!has_body(
+        "HEAD",
+        "HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\n"
+    ));
+    assert!(!has_body(
+        "GET",
+        "HTTP/1.1 204 No Content\r\nContent-Length: 6\r\n\r\n"
+    ));
+    assert!(!has_body("GET", "HTTP/1.1 304 Not Modified\r\n\r\n"));
+}
@@ -0,0 +1,218 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![allow(clippy::field_reassign_with_default)]
+
+mod common;
+
+use std::io::{BufReader, ErrorKind, Write};
+use std::net::TcpListener;
+use std::ops::ControlFlow;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use zeptohttpc::{
+    http::Request, Client, Error, Options, RequestBuilderExt, ResponseExt, StreamingBody,
+};
+
+use common::{read_request, MockServer};
+
+fn opts() -> Options<'static> {
+    let mut opts = Options::default();
+    opts.deadline = Some(Instant::now() + Duration::from_secs(5));
+    opts
+}
+
+#[test]
+fn reuses_kept_alive_connection() {
+    let mock = MockServer::start_keep_alive(vec![
+        "HTTP/1.1 200 Ok\r\nContent-Length: 5\r\n\r\nfirst",
+        "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nsecond\r\n0\r\n\r\n",
+        "HTTP/1.1 204 No Content\r\n\r\n",
+    ]);
+
+    let client = Client::new(opts());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("first", resp.into_string().unwrap());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("second", resp.into_string().unwrap());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!(204, resp.status());
+    drop(resp);
+
+    let reqs = mock.requests();
+    assert_eq!(reqs.len(), 3);
+    assert!(reqs.iter().all(|req| !req.contains("connection: close")));
+}
+
+#[test]
+fn reconnects_if_idle_connection_was_closed() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.1 200 Ok\r\nContent-Length: 5\r\n\r\nfirst",
+        "HTTP/1.1 200 Ok\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nsecond\r\n0\r\n\r\n",
+    ]);
+
+    let client = Client::new(opts());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("first", resp.into_string().unwrap());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("second", resp.into_string().unwrap());
+
+    drop(client);
+
+    let reqs = mock.requests();
+    assert_eq!(reqs[0].matches("GET / HTTP/1.1").count(), 2);
+    assert_eq!(reqs[1].matches("GET / HTTP/1.1").count(), 1);
+}
+
+#[test]
+fn does_not_retry_streaming_body_on_closed_idle_connection() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.1 200 Ok\r\nContent-Length: 5\r\n\r\nfirst",
+        "HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\nsecond",
+    ]);
+
+    let client = Client::new(opts());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("first", resp.into_string().unwrap());
+
+    let mut sent = false;
+
+    let body = StreamingBody(move |writer: &mut dyn Write| {
+        if sent {
+            return Ok(ControlFlow::Break(()));
+        }
+
+        writer.write_all(b"foobar")?;
+        sent = true;
+        Ok(ControlFlow::Continue(()))
+    });
+
+    assert!(client
+        .send(Request::post(mock.uri()).body(body).unwrap())
+        .is_err());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("second", resp.into_string().unwrap());
+
+    drop(client);
+
+    let reqs = mock.requests();
+    assert_eq!(reqs[0].matches("POST / HTTP/1.1").count(), 1);
+    assert_eq!(reqs[1].matches("POST / HTTP/1.1").count(), 0);
+}
+
+#[test]
+fn times_out_if_reused_connection_stalls() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(move || {
+        let (mut stream, _peer_addr) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        read_request(&mut reader);
+        stream
+            .write_all(b"HTTP/1.1 200 Ok\r\nContent-Length: 5\r\n\r\nfirst")
+            .unwrap();
+
+        read_request(&mut reader);
+        sleep(Duration::from_millis(1000));
+    });
+
+    let mut opts = opts();
+    opts.idle_timeout = Some(Duration::from_millis(100));
+
+    let client = Client::new(opts);
+    let uri = format!("http://localhost:{}", port);
+
+    let resp = client.send(Request::get(&uri).empty().unwrap()).unwrap();
+    assert_eq!("first", resp.into_string().unwrap());
+
+    // Idling in the pool for longer than the idle timeout must not close the connection.
+    sleep(Duration::from_millis(200));
+
+    let started = Instant::now();
+
+    match client.send(Request::get(&uri).empty().unwrap()) {
+        Err(Error::Io(err)) => {
+            assert_eq!(ErrorKind::TimedOut, err.kind());
+            assert!(started.elapsed() < Duration::from_millis(1000));
+        }
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+
+    server.join().unwrap();
+}
+
+#[test]
+fn does_not_reuse_connection_closed_by_server() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.1 200 Ok\r\nContent-Length: 5\r\nConnection: close\r\n\r\nfirst",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 6\r\n\r\nsecond",
+        "HTTP/1.1 200 Ok\r\nContent-Length: 5\r\n\r\nthird",
+    ]);
+
+    let client = Client::new(opts());
+
+    for body in ["first", "second", "third"] {
+        let resp = client
+            .send(Request::get(mock.uri()).empty().unwrap())
+            .unwrap();
+        assert_eq!(body, resp.into_string().unwrap());
+    }
+
+    drop(client);
+
+    let reqs = mock.requests();
+    assert!(reqs.iter().all(|req| req.matches("GET /").count() == 1));
+}
+
+#[test]
+fn reuses_connection_for_same_origin_redirect() {
+    let mock = MockServer::start_keep_alive(vec![
+        "HTTP/1.1 301 Moved Permanently\r\nLocation: /redirected\r\nContent-Length: 8\r\n\r\nnot here",
+        "HTTP/1.1 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+
+    let client = Client::new(opts());
+
+    let resp = client
+        .send(Request::get(mock.uri()).empty().unwrap())
+        .unwrap();
+    assert_eq!("redirected", resp.into_string().unwrap());
+
+    let reqs = mock.requests();
+    assert!(reqs[1].starts_with("GET /redirected HTTP/1.1\r\n"));
+}
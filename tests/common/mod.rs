@@ -0,0 +1,194 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Cursor, Read, Result as IoResult, Write};
+use std::net::{Shutdown, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+
+pub struct MockServer {
+    port: u16,
+    server: Option<JoinHandle<Vec<String>>>,
+}
+
+impl MockServer {
+    pub fn start(resps: Vec<&'static str>) -> Self {
+        Self::start_after_resets(0, resps)
+    }
+
+    /// Resets the given number of connections after receiving the start of a request before serving the responses.
+    pub fn start_after_resets(resets: usize, resps: Vec<&'static str>) -> Self {
+        Self::start_with(resets, |port| {
+            resps
+                .into_iter()
+                .map(|resp| {
+                    resp.replace("{uri}", &format!("http://localhost:{}", port))
+                        .into_bytes()
+                })
+                .collect()
+        })
+    }
+
+    pub fn start_bytes(resps: Vec<Vec<u8>>) -> Self {
+        Self::start_with(0, |_port| resps)
+    }
+
+    fn start_with<F>(resets: usize, resps: F) -> Self
+    where
+        F: FnOnce(u16) -> Vec<Vec<u8>>,
+    {
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let resps = resps(port);
+
+        let server = spawn(move || {
+            for _ in 0..resets {
+                let (stream, _peer_addr) = listener.accept().unwrap();
+
+                // Closing the socket with unread data makes the kernel send a reset.
+                stream.peek(&mut [0]).unwrap();
+            }
+
+            let mut reqs = Vec::new();
+
+            for resp in resps {
+                let (mut stream, _peer_addr) = listener.accept().unwrap();
+
+                stream.write_all(&resp).unwrap();
+                stream.shutdown(Shutdown::Write).unwrap();
+
+                let mut buf = Vec::new();
+                stream.read_to_end(&mut buf).unwrap();
+                reqs.push(String::from_utf8_lossy(&buf).into_owned());
+            }
+
+            reqs
+        });
+
+        Self {
+            port,
+            server: Some(server),
+        }
+    }
+
+    /// Serves all responses over a single connection, writing each one after reading a complete request.
+    ///
+    /// Responses must be delimited, e.g. using `Content-Length`, so that the client can find their end.
+    /// Request bodies are read according to their `Content-Length` header.
+    pub fn start_keep_alive(resps: Vec<&'static str>) -> Self {
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = spawn(move || {
+            let (mut stream, _peer_addr) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut reqs = Vec::new();
+
+            for resp in resps {
+                reqs.push(read_request(&mut reader));
+
+                stream.write_all(resp.as_bytes()).unwrap();
+            }
+
+            stream.shutdown(Shutdown::Write).unwrap();
+
+            reqs
+        });
+
+        Self {
+            port,
+            server: Some(server),
+        }
+    }
+
+    pub fn uri(&self) -> String {
+        format!("http://localhost:{}", self.port)
+    }
+
+    pub fn requests(mut self) -> Vec<String> {
+        self.server.take().unwrap().join().unwrap()
+    }
+}
+
+pub fn read_request<R: BufRead>(reader: &mut R) -> String {
+    let mut req = String::new();
+    let mut len = 0;
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        req.push_str(&line);
+
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = value.trim().parse().unwrap();
+            }
+        }
+    }
+
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+    req.push_str(&String::from_utf8_lossy(&body));
+
+    req
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(server) = self.server.take() {
+            server.join().unwrap();
+        }
+    }
+}
+
+pub struct MockStream {
+    resp: Cursor<Vec<u8>>,
+    req: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MockStream {
+    pub fn new(resp: impl Into<Vec<u8>>) -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let req = Arc::new(Mutex::new(Vec::new()));
+
+        let stream = Self {
+            resp: Cursor::new(resp.into()),
+            req: req.clone(),
+        };
+
+        (stream, req)
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.resp.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.req.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![allow(clippy::field_reassign_with_default)]
+
+mod common;
+
+use std::io::{Error as IoError, ErrorKind, Result as IoResult, Write};
+use std::net::TcpStream;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use zeptohttpc::{
+    http::Request, Error, Options, ProxyProtocol, RequestBuilderExt, RequestExt, StreamingBody,
+};
+
+use common::MockServer;
+
+#[test]
+fn configures_socket_after_connect() {
+    let mock = MockServer::start(vec!["HTTP/1.1 204 No Content\r\n\r\n"]);
+
+    let configured = AtomicBool::new(false);
+    let configure_socket = |stream: &TcpStream| -> IoResult<()> {
+        stream.set_nodelay(true)?;
+        assert!(stream.nodelay()?);
+
+        configured.store(true, Ordering::SeqCst);
+        Ok(())
+    };
+
+    let mut opts = Options::default();
+    opts.configure_socket = Some(&configure_socket);
+
+    let resp = Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    assert_eq!(204, resp.status());
+    assert!(configured.load(Ordering::SeqCst));
+}
+
+#[test]
+fn fails_if_socket_cannot_be_configured() {
+    let mock = MockServer::start(vec![""]);
+
+    let configure_socket =
+        |_stream: &TcpStream| -> IoResult<()> { Err(IoError::new(ErrorKind::Other, "nope")) };
+
+    let mut opts = Options::default();
+    opts.configure_socket = Some(&configure_socket);
+
+    let res = Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::Io(err)) => assert_eq!(ErrorKind::Other, err.kind()),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn retries_after_connection_reset() {
+    let mock = MockServer::start_after_resets(1, vec!["HTTP/1.1 204 No Content\r\n\r\n"]);
+
+    let mut opts = Options::default();
+    opts.retries = 2;
+    opts.connect_delay = Duration::from_millis(10);
+
+    let resp = Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+
+    let reqs = mock.requests();
+    assert_eq!(reqs.len(), 1);
+    assert!(reqs[0].starts_with("GET / HTTP/1.1\r\n"));
+}
+
+#[test]
+fn does_not_retry_unless_body_is_replayable() {
+    let mock = MockServer::start_after_resets(1, vec![]);
+
+    let mut opts = Options::default();
+    opts.retries = 2;
+    opts.connect_delay = Duration::from_millis(10);
+
+    let mut sent = false;
+
+    let res = Request::post(mock.uri())
+        .body(StreamingBody(|writer: &mut dyn Write| {
+            assert!(!sent);
+            sent = true;
+
+            writer.write_all(b"foobar")?;
+            Ok(ControlFlow::Break(()))
+        }))
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::Io(err)) => assert!(matches!(
+            err.kind(),
+            ErrorKind::ConnectionReset | ErrorKind::BrokenPipe
+        )),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn sends_proxy_protocol_header() {
+    let mock = MockServer::start(vec!["HTTP/1.1 204 No Content\r\n\r\n"]);
+
+    let mut opts = Options::default();
+    opts.proxy_protocol = Some(ProxyProtocol::V1);
+
+    let resp = Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    assert_eq!(204, resp.status());
+
+    let reqs = mock.requests();
+    assert!(reqs[0].starts_with("PROXY TCP"));
+
+    let (header, req) = reqs[0].split_once("\r\n").unwrap();
+    assert_eq!(6, header.split(' ').count());
+    assert!(req.starts_with("GET / HTTP/1.1\r\n"));
+}
+
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+#[test]
+fn selects_tls_backend() {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread::spawn;
+
+    use zeptohttpc::{rustls, TlsBackend};
+
+    for tls_backend in [TlsBackend::NativeTls, TlsBackend::Rustls] {
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = spawn(move || {
+            let (mut stream, _peer_addr) = listener.accept().unwrap();
+
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+        });
+
+        let mut opts = Options::default();
+        opts.tls_backend = tls_backend;
+
+        let res = Request::get(format!("https://localhost:{}", port))
+            .empty()
+            .unwrap()
+            .send_with_opts(opts);
+
+        match res {
+            Err(Error::NativeTls(_)) => assert_eq!(tls_backend, TlsBackend::NativeTls),
+            Err(Error::Io(err)) if err.get_ref().map_or(false, |err| err.is::<rustls::Error>()) => {
+                assert_eq!(tls_backend, TlsBackend::Rustls)
+            }
+            Err(err) => panic!("Unexpected error: {}", err),
+            Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+        }
+
+        server.join().unwrap();
+    }
+}
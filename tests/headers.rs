@@ -0,0 +1,393 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![allow(clippy::field_reassign_with_default)]
+
+mod common;
+
+use std::io::ErrorKind;
+
+use zeptohttpc::{
+    http::{
+        header::{ACCEPT_LANGUAGE, VIA},
+        HeaderValue, Request,
+    },
+    Error, Options, RawHeaders, RequestBuilderExt, RequestExt, ResponseExt, UserAgent,
+};
+
+use common::{MockServer, MockStream};
+
+#[test]
+fn sends_default_accept_headers() {
+    let mock = MockServer::start(vec!["HTTP/1.0 204 No Content\r\n\r\n"]);
+
+    let accept = HeaderValue::from_static("text/html");
+    let accept_language = HeaderValue::from_static("de, en;q=0.5");
+
+    let mut opts = Options::default();
+    opts.accept = Some(&accept);
+    opts.accept_language = Some(&accept_language);
+
+    Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let reqs = mock.requests();
+    assert!(reqs[0].contains("accept: text/html\r\n"));
+    assert!(reqs[0].contains("accept-language: de, en;q=0.5\r\n"));
+}
+
+#[test]
+fn does_not_override_accept_headers() {
+    let mock = MockServer::start(vec!["HTTP/1.0 204 No Content\r\n\r\n"]);
+
+    let accept_language = HeaderValue::from_static("de");
+
+    let mut opts = Options::default();
+    opts.accept_language = Some(&accept_language);
+
+    Request::get(mock.uri())
+        .header(ACCEPT_LANGUAGE, "fr")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let reqs = mock.requests();
+    assert!(reqs[0].contains("accept-language: fr\r\n"));
+    assert!(!reqs[0].contains("accept-language: de\r\n"));
+}
+
+#[test]
+fn sends_wildcard_accept_header_if_enabled() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 204 No Content\r\n\r\n",
+        "HTTP/1.0 204 No Content\r\n\r\n",
+        "HTTP/1.0 204 No Content\r\n\r\n",
+    ]);
+
+    let send = |opts| {
+        Request::get(mock.uri())
+            .empty()
+            .unwrap()
+            .send_with_opts(opts)
+            .unwrap();
+    };
+
+    send(Options::default());
+
+    let mut opts = Options::default();
+    opts.default_accept = true;
+    send(opts);
+
+    let accept = HeaderValue::from_static("text/html");
+    opts.accept = Some(&accept);
+    send(opts);
+
+    let reqs = mock.requests();
+    assert!(!reqs[0].contains("accept:"));
+    assert!(reqs[1].contains("accept: */*\r\n"));
+    assert!(reqs[2].contains("accept: text/html\r\n"));
+}
+
+#[test]
+fn sends_custom_user_agent() {
+    let mock = MockServer::start(vec!["HTTP/1.0 204 No Content\r\n\r\n"]);
+
+    let user_agent = UserAgent::new()
+        .product("MyBot", Some("1.0"))
+        .unwrap()
+        .comment("+https://example.com/bot")
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let mut opts = Options::default();
+    opts.user_agent = Some(&user_agent);
+
+    Request::get(mock.uri())
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let reqs = mock.requests();
+    assert!(reqs[0].contains("user-agent: MyBot/1.0 (+https://example.com/bot)\r\n"));
+}
+
+#[test]
+fn appends_via_header() {
+    let mock = MockServer::start(vec!["HTTP/1.0 204 No Content\r\n\r\n"]);
+
+    let mut opts = Options::default();
+    opts.via = Some("zepto");
+    opts.detect_loops = true;
+
+    Request::get(mock.uri())
+        .header(VIA, "1.1 other")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let reqs = mock.requests();
+    assert!(reqs[0].contains("via: 1.1 other\r\nvia: 1.1 zepto\r\n"));
+}
+
+#[test]
+fn fails_due_to_loop() {
+    let mut opts = Options::default();
+    opts.via = Some("zepto");
+    opts.detect_loops = true;
+
+    let res = Request::get("http://localhost")
+        .header(VIA, "1.1 other, 1.1 zepto")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::LoopDetected) => (),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn unfolds_obsolete_line_folding() {
+    let (stream, _req) = MockStream::new(
+        "HTTP/1.1 200 Ok\r\nX-Long: foo\r\n bar\r\n\tbaz\r\nContent-Length: 0\r\n\r\n",
+    );
+
+    let mut opts = Options::default();
+    opts.allow_obsolete_folding = true;
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts)
+        .unwrap();
+
+    assert_eq!(resp.headers()["x-long"], "foo bar baz");
+    assert_eq!(resp.headers()["content-length"], "0");
+}
+
+#[test]
+fn rejects_obsolete_line_folding_by_default() {
+    let (stream, _req) =
+        MockStream::new("HTTP/1.1 200 Ok\r\nX-Long: foo\r\n bar\r\nContent-Length: 0\r\n\r\n");
+
+    let res = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Options::default());
+
+    match res {
+        Err(Error::Httparse(_)) => (),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn rejects_headers_exceeding_limit() {
+    for read_buffer_size in [8 * 1024, 16] {
+        let (stream, _req) = MockStream::new(format!(
+            "HTTP/1.1 200 Ok\r\nX-Padding: {}\r\nContent-Length: 0\r\n\r\n",
+            "x".repeat(1024)
+        ));
+
+        let mut opts = Options::default();
+        opts.max_header_bytes = 512;
+        opts.read_buffer_size = read_buffer_size;
+
+        let res = Request::get("http://localhost")
+            .empty()
+            .unwrap()
+            .send_over(stream, opts);
+
+        match res {
+            Err(Error::Io(err)) => assert_eq!(ErrorKind::Other, err.kind()),
+            Err(err) => panic!("Unexpected error: {}", err),
+            Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+        }
+    }
+}
+
+#[test]
+fn skips_leading_blank_lines_if_tolerant() {
+    let (stream, _req) = MockStream::new("\r\n\r\n  HTTP/1.1 204 No Content\r\n\r\n");
+
+    let mut opts = Options::default();
+    opts.tolerant_parsing = true;
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts)
+        .unwrap();
+
+    assert_eq!(204, resp.status());
+}
+
+#[test]
+fn rejects_excessive_leading_whitespace() {
+    let (stream, _req) =
+        MockStream::new(format!("{}HTTP/1.1 204 No Content\r\n\r\n", " ".repeat(64)));
+
+    let mut opts = Options::default();
+    opts.tolerant_parsing = true;
+
+    let res = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts);
+
+    match res {
+        Err(Error::Httparse(_)) => (),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn captures_input_of_failed_parse() {
+    let (stream, _req) = MockStream::new("HTTP/1.1 200 Ok\r\nBad Header\r\n\r\n");
+
+    let mut opts = Options::default();
+    opts.capture_parse_errors = true;
+
+    let res = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts);
+
+    match res {
+        Err(Error::HttparseInput(_, input)) => {
+            assert_eq!(b"HTTP/1.1 200 Ok\r\nBad Header\r\n\r\n", &input[..])
+        }
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+}
+
+#[test]
+fn preserves_raw_headers() {
+    let (stream, _req) = MockStream::new(
+        "HTTP/1.1 200 Ok\r\nX-Foo: 1\r\nContent-Length: 0\r\nx-foo: 2\r\nX-FOO: 3\r\n\r\n",
+    );
+
+    let mut opts = Options::default();
+    opts.preserve_raw_headers = true;
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, opts)
+        .unwrap();
+
+    let raw_headers = resp.extensions().get::<RawHeaders>().unwrap();
+
+    assert_eq!(
+        raw_headers.iter().collect::<Vec<_>>(),
+        [
+            ("X-Foo", b"1".as_ref()),
+            ("Content-Length", b"0".as_ref()),
+            ("x-foo", b"2".as_ref()),
+            ("X-FOO", b"3".as_ref()),
+        ]
+    );
+    assert_eq!(
+        raw_headers.get_all("x-foo").collect::<Vec<_>>(),
+        [b"1".as_ref(), b"2".as_ref(), b"3".as_ref()]
+    );
+}
+
+#[test]
+fn does_not_preserve_raw_headers_by_default() {
+    let (stream, _req) = MockStream::new("HTTP/1.1 200 Ok\r\nContent-Length: 0\r\n\r\n");
+
+    let resp = Request::get("http://localhost")
+        .empty()
+        .unwrap()
+        .send_over(stream, Options::default())
+        .unwrap();
+
+    assert!(resp.extensions().get::<RawHeaders>().is_none());
+}
+
+#[test]
+fn resolves_content_location() {
+    let (stream, _req) =
+        MockStream::new("HTTP/1.1 200 Ok\r\nContent-Location: ../c?d\r\nContent-Length: 0\r\n\r\n");
+
+    let resp = Request::get("http://example.com/a/b/index")
+        .empty()
+        .unwrap()
+        .send_over(stream, Options::default())
+        .unwrap();
+
+    assert_eq!(resp.content_location().unwrap(), "http://example.com/a/c?d");
+}
+
+#[test]
+fn resolves_links_for_pagination() {
+    let (stream, _req) = MockStream::new(
+        "HTTP/1.1 200 Ok\r\nLink: <?page=3>; rel=\"next\", <?page=1>; rel=\"prev\"\r\nLink: <http://example.org/items>; rel=canonical\r\nContent-Length: 0\r\n\r\n",
+    );
+
+    let resp = Request::get("http://example.com/items?page=2")
+        .empty()
+        .unwrap()
+        .send_over(stream, Options::default())
+        .unwrap();
+
+    let links = resp.links();
+    assert_eq!(links.len(), 3);
+
+    let next = links.iter().find(|link| link.has_rel("next")).unwrap();
+    assert_eq!(next.uri, "http://example.com/items?page=3");
+
+    let canonical = links.iter().find(|link| link.has_rel("canonical")).unwrap();
+    assert_eq!(canonical.uri, "http://example.org/items");
+}
+
+#[test]
+fn sends_conditional_requests_using_etag_cache() {
+    use zeptohttpc::{ETagCache, Polled};
+
+    let mock = MockServer::start(vec![
+        "HTTP/1.1 200 Ok\r\nETag: \"v1\"\r\nContent-Length: 6\r\n\r\nfoobar",
+        "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\n\r\n",
+    ]);
+
+    let cache = ETagCache::new();
+
+    let req = || Request::get(mock.uri()).empty().unwrap();
+
+    match cache.send(req(), Options::default()).unwrap() {
+        Polled::Modified(resp) => assert_eq!("foobar", resp.into_string().unwrap()),
+        Polled::NotModified => panic!("Unexpected not modified"),
+    }
+
+    assert!(matches!(
+        cache.send(req(), Options::default()).unwrap(),
+        Polled::NotModified
+    ));
+
+    let reqs = mock.requests();
+    assert!(!reqs[0].contains("if-none-match"));
+    assert!(reqs[1].contains("if-none-match: \"v1\"\r\n"));
+}
@@ -0,0 +1,97 @@
+// Copyright 2020 Adam Reichold
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![allow(clippy::field_reassign_with_default)]
+
+mod common;
+
+use zeptohttpc::{
+    http::{Request, Uri},
+    Options, RequestBuilderExt, RequestExt, ResponseExt,
+};
+
+use common::MockServer;
+
+#[test]
+fn sends_absolute_form_via_proxy() {
+    let mock = MockServer::start(vec!["HTTP/1.1 200 Ok\r\nContent-Length: 7\r\n\r\nproxied"]);
+
+    let proxy = mock.uri().parse::<Uri>().unwrap();
+
+    let mut opts = Options::default();
+    opts.proxy = Some(&proxy);
+
+    let resp = Request::get("http://example.invalid:8080/foo?bar=baz")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let body = resp.into_string().unwrap();
+    assert_eq!("proxied", body);
+
+    let reqs = mock.requests();
+    assert!(reqs[0].starts_with("GET http://example.invalid:8080/foo?bar=baz HTTP/1.1\r\n"));
+    assert!(reqs[0].contains("host: example.invalid\r\n"));
+}
+
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+#[test]
+fn tunnels_https_via_proxy() {
+    let mock = MockServer::start(vec!["HTTP/1.1 200 Connection established\r\n\r\n"]);
+
+    let proxy = mock.uri().parse::<Uri>().unwrap();
+
+    let mut opts = Options::default();
+    opts.proxy = Some(&proxy);
+
+    // The mock proxy does not actually forward the TLS handshake.
+    let res = Request::get("https://example.invalid/foo")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+    assert!(res.is_err());
+
+    let reqs = mock.requests();
+    assert!(reqs[0]
+        .starts_with("CONNECT example.invalid:443 HTTP/1.1\r\nhost: example.invalid:443\r\n\r\n"));
+}
+
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+#[test]
+fn fails_if_proxy_refuses_tunnel() {
+    use zeptohttpc::Error;
+
+    let mock = MockServer::start(vec![
+        "HTTP/1.1 407 Proxy Authentication Required\r\nContent-Length: 0\r\n\r\n",
+    ]);
+
+    let proxy = mock.uri().parse::<Uri>().unwrap();
+
+    let mut opts = Options::default();
+    opts.proxy = Some(&proxy);
+
+    let res = Request::get("https://example.invalid:8443/foo")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::ProxyConnect(status)) => assert_eq!(407, status),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+
+    let reqs = mock.requests();
+    assert!(reqs[0].starts_with("CONNECT example.invalid:8443 HTTP/1.1\r\n"));
+}
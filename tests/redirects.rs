@@ -13,12 +13,12 @@
 // limitations under the License.
 #![allow(clippy::field_reassign_with_default)]
 
-use std::io::{Read, Write};
-use std::net::{Shutdown, TcpListener};
-use std::thread::{spawn, JoinHandle};
+mod common;
 
 use zeptohttpc::{http::Request, Error, Options, RequestBuilderExt, RequestExt, ResponseExt};
 
+use common::{MockServer, MockStream};
+
 #[test]
 fn redirects_for_moved_permanently() {
     let mock = MockServer::start(vec![
@@ -39,9 +39,10 @@ fn does_not_redirect_for_not_modified() {
     ]);
 
     let resp = Request::get(mock.uri()).empty().unwrap().send().unwrap();
+    assert_eq!(304, resp.status());
 
     let body = resp.into_string().unwrap();
-    assert_eq!("unchanged", body);
+    assert_eq!("", body);
 }
 
 #[test]
@@ -101,45 +102,217 @@ fn location_is_recommended_but_not_required() {
     }
 }
 
-struct MockServer {
-    port: u16,
-    server: Option<JoinHandle<()>>,
+#[test]
+fn resolves_relative_redirect() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 301 Moved Permanently\r\nLocation: ../baz?qux\r\n\r\n",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+
+    let resp = Request::get(format!("{}/foo/bar/index", mock.uri()))
+        .empty()
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!("redirected", resp.into_string().unwrap());
+
+    let reqs = mock.requests();
+    assert!(reqs[1].starts_with("GET /foo/baz?qux HTTP/1.1\r\n"));
 }
 
-impl MockServer {
-    fn start(resps: Vec<&'static str>) -> Self {
-        let listener = TcpListener::bind("localhost:0").unwrap();
-        let port = listener.local_addr().unwrap().port();
+#[test]
+fn strips_fragment_from_redirect() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 301 Moved Permanently\r\nLocation: ../baz#section?not-a-query\r\n\r\n",
+        "HTTP/1.0 302 Found\r\nLocation: #top\r\n\r\n",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+
+    let resp = Request::get(format!("{}/foo/bar?qux", mock.uri()))
+        .empty()
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!("redirected", resp.into_string().unwrap());
+
+    let reqs = mock.requests();
+    assert!(reqs[1].starts_with("GET /baz HTTP/1.1\r\n"));
+    assert!(reqs[2].starts_with("GET /baz HTTP/1.1\r\n"));
+}
+
+#[test]
+fn drops_credentials_when_redirecting_to_other_authority() {
+    let target = MockServer::start(vec![
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+    let mock = MockServer::start_bytes(vec![format!(
+        "HTTP/1.0 302 Found\r\nLocation: {}/other\r\n\r\n",
+        target.uri()
+    )
+    .into_bytes()]);
+
+    let resp = Request::get(mock.uri())
+        .bearer_auth("secret")
+        .header("cookie", "foo=bar")
+        .empty()
+        .unwrap()
+        .send()
+        .unwrap();
 
-        let server = spawn(move || {
-            let port = port.to_string();
+    assert_eq!("redirected", resp.into_string().unwrap());
 
-            for resp in resps {
-                let resp = resp.replace("{uri}", &format!("http://localhost:{}", port));
+    let reqs = mock.requests();
+    assert!(reqs[0].contains("authorization: Bearer secret\r\n"));
+    assert!(reqs[0].contains("cookie: foo=bar\r\n"));
 
-                let (mut stream, _peer_addr) = listener.accept().unwrap();
+    let reqs = target.requests();
+    assert!(reqs[0].starts_with("GET /other HTTP/1.1\r\n"));
+    assert!(!reqs[0].contains("authorization:"));
+    assert!(!reqs[0].contains("cookie:"));
+}
 
-                stream.write_all(resp.as_bytes()).unwrap();
-                stream.shutdown(Shutdown::Write).unwrap();
+#[test]
+fn keeps_credentials_when_redirecting_to_same_authority() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 302 Found\r\nLocation: {uri}/other\r\n\r\n",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
 
-                let mut buf = Vec::new();
-                stream.read_to_end(&mut buf).unwrap();
-            }
-        });
+    let resp = Request::get(mock.uri())
+        .bearer_auth("secret")
+        .empty()
+        .unwrap()
+        .send()
+        .unwrap();
 
-        Self {
-            port,
-            server: Some(server),
-        }
-    }
+    assert_eq!("redirected", resp.into_string().unwrap());
 
-    fn uri(&self) -> String {
-        format!("http://localhost:{}", self.port)
-    }
+    let reqs = mock.requests();
+    assert!(reqs[1].starts_with("GET /other HTTP/1.1\r\n"));
+    assert!(reqs[1].contains("authorization: Bearer secret\r\n"));
 }
 
-impl Drop for MockServer {
-    fn drop(&mut self) {
-        self.server.take().unwrap().join().unwrap();
-    }
+#[test]
+fn follows_redirects_manually() {
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 303 See Other\r\nLocation: /status\r\nContent-Length: 8\r\n\r\nnot here",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+
+    let req = Request::post(format!("{}/submit", mock.uri()))
+        .header("authorization", "Bearer token")
+        .from_mem("foobar")
+        .unwrap();
+
+    let resp = Request::post(req.uri())
+        .from_mem("foobar")
+        .unwrap()
+        .send_once(Options::default())
+        .unwrap();
+    assert_eq!(303, resp.status());
+
+    let next = resp.next_request(&req).unwrap().unwrap();
+    assert_eq!("GET", next.method());
+    assert_eq!(format!("{}/status", mock.uri()), next.uri().to_string());
+    assert_eq!("Bearer token", next.headers()["authorization"]);
+    assert!(!next.headers().contains_key("content-type"));
+    assert_eq!("not here", resp.into_string().unwrap());
+
+    let resp = next.send_once(Options::default()).unwrap();
+    assert!(resp.next_request(&req).is_none());
+    assert_eq!("redirected", resp.into_string().unwrap());
+
+    let reqs = mock.requests();
+    assert!(reqs[0].starts_with("POST /submit HTTP/1.1\r\n"));
+    assert!(reqs[1].starts_with("GET /status HTTP/1.1\r\n"));
+}
+
+#[test]
+fn drops_credentials_when_redirecting_manually_to_other_authority() {
+    let (stream, _req) = MockStream::new(
+        "HTTP/1.1 307 Temporary Redirect\r\nLocation: http://other.example.com/\r\nContent-Length: 0\r\n\r\n",
+    );
+
+    let req = Request::put("http://example.com/foo")
+        .header("authorization", "Bearer token")
+        .header("cookie", "foo=bar")
+        .header("x-custom", "baz")
+        .from_mem("foobar")
+        .unwrap();
+
+    let resp = Request::put("http://example.com/foo")
+        .empty()
+        .unwrap()
+        .send_over(stream, Options::default())
+        .unwrap();
+
+    let next = resp.next_request(&req).unwrap().unwrap();
+    assert_eq!("PUT", next.method());
+    assert_eq!("http://other.example.com/", next.uri().to_string());
+    assert!(!next.headers().contains_key("authorization"));
+    assert!(!next.headers().contains_key("cookie"));
+    assert_eq!("baz", next.headers()["x-custom"]);
+}
+
+#[test]
+fn sends_cookies_set_before_redirect() {
+    use zeptohttpc::CookieJar;
+
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 302 Found\r\nSet-Cookie: session=abc; Path=/; HttpOnly\r\nLocation: {uri}/next\r\nContent-Length: 0\r\n\r\n",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+
+    let jar = CookieJar::new();
+
+    let mut opts = Options::default();
+    opts.cookies = Some(&jar);
+
+    let resp = Request::get(format!("{}/login", mock.uri()))
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    assert_eq!("redirected", resp.into_string().unwrap());
+
+    let uri = mock.uri().parse().unwrap();
+    assert_eq!(Some("abc"), jar.get(&uri, "session").as_deref());
+
+    let reqs = mock.requests();
+    assert!(!reqs[0].contains("cookie:"));
+    assert!(reqs[1].starts_with("GET /next HTTP/1.1\r\n"));
+    assert!(reqs[1].contains("cookie: session=abc\r\n"));
+}
+
+#[test]
+fn appends_cookies_from_jar_to_those_of_request() {
+    use zeptohttpc::CookieJar;
+
+    let mock = MockServer::start(vec![
+        "HTTP/1.0 302 Found\r\nSet-Cookie: session=abc; Path=/\r\nLocation: {uri}/next\r\nContent-Length: 0\r\n\r\n",
+        "HTTP/1.0 302 Found\r\nLocation: {uri}/last\r\nContent-Length: 0\r\n\r\n",
+        "HTTP/1.0 200 Ok\r\nContent-Length: 10\r\n\r\nredirected",
+    ]);
+
+    let jar = CookieJar::new();
+
+    let mut opts = Options::default();
+    opts.cookies = Some(&jar);
+
+    let resp = Request::get(format!("{}/login", mock.uri()))
+        .header("cookie", "theme=dark")
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    assert_eq!("redirected", resp.into_string().unwrap());
+
+    let reqs = mock.requests();
+    assert!(reqs[0].contains("cookie: theme=dark\r\n"));
+    assert!(reqs[1].contains("cookie: theme=dark; session=abc\r\n"));
+    assert!(reqs[2].contains("cookie: theme=dark; session=abc\r\n"));
 }
@@ -13,12 +13,12 @@
 // limitations under the License.
 #![allow(clippy::field_reassign_with_default)]
 
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Write};
 use std::net::TcpListener;
-use std::thread::{sleep, spawn};
+use std::thread::{sleep, spawn, JoinHandle};
 use std::time::{Duration, Instant};
 
-use zeptohttpc::{http::Request, Error, Options, RequestBuilderExt, RequestExt};
+use zeptohttpc::{http::Request, Error, Options, RequestBuilderExt, RequestExt, ResponseExt};
 
 #[test]
 fn fails_due_to_timeout() {
@@ -47,3 +47,185 @@ fn fails_due_to_timeout() {
 
     server.join().unwrap();
 }
+
+#[test]
+fn fails_due_to_timeout_set_per_request() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(move || {
+        let (_stream, _peer_addr) = listener.accept().unwrap();
+
+        sleep(Duration::from_millis(500));
+    });
+
+    let res = Request::get(format!("http://localhost:{}", port))
+        .empty()
+        .unwrap()
+        .send_with_timeout(Duration::from_millis(100));
+
+    match res {
+        Err(Error::Io(err)) => assert_eq!(ErrorKind::TimedOut, err.kind()),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+
+    server.join().unwrap();
+}
+
+/// Serves a body of six bytes, waiting the given number of milliseconds before writing each one.
+fn serve_slowly(delays: [u64; 6]) -> (u16, JoinHandle<()>) {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(move || {
+        let (mut stream, _peer_addr) = listener.accept().unwrap();
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n")
+            .unwrap();
+
+        for (byte, delay) in b"foobar".iter().zip(delays) {
+            sleep(Duration::from_millis(delay));
+
+            if stream.write_all(&[*byte]).is_err() {
+                return;
+            }
+        }
+    });
+
+    (port, server)
+}
+
+#[test]
+fn idle_timeout_is_reset_by_received_data() {
+    let (port, server) = serve_slowly([50; 6]);
+
+    let mut opts = Options::default();
+    opts.idle_timeout = Some(Duration::from_millis(200));
+
+    let body = Request::get(format!("http://localhost:{}", port))
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap()
+        .into_string()
+        .unwrap();
+    assert_eq!(body, "foobar");
+
+    server.join().unwrap();
+}
+
+#[test]
+fn fails_due_to_idle_timeout() {
+    let (port, server) = serve_slowly([0, 0, 0, 500, 0, 0]);
+
+    let mut opts = Options::default();
+    opts.idle_timeout = Some(Duration::from_millis(200));
+
+    let resp = Request::get(format!("http://localhost:{}", port))
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    let err = resp.into_string().unwrap_err();
+    assert_eq!(ErrorKind::TimedOut, err.kind());
+
+    server.join().unwrap();
+}
+
+#[cfg(any(
+    feature = "native-tls",
+    feature = "tls-webpki-roots",
+    feature = "tls-native-roots"
+))]
+#[test]
+fn fails_due_to_tls_handshake_timeout() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(move || {
+        let (_stream, _peer_addr) = listener.accept().unwrap();
+
+        sleep(Duration::from_millis(500));
+    });
+
+    let mut opts = Options::default();
+    opts.deadline = Some(Instant::now() + Duration::from_millis(100));
+
+    let res = Request::get(format!("https://localhost:{}", port))
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::TlsHandshakeTimeout) => (),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+
+    server.join().unwrap();
+}
+
+#[test]
+fn fails_due_to_first_byte_timeout() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(move || {
+        let (_stream, _peer_addr) = listener.accept().unwrap();
+
+        sleep(Duration::from_millis(500));
+    });
+
+    let mut opts = Options::default();
+    opts.first_byte_timeout = Some(Duration::from_millis(100));
+
+    let started = Instant::now();
+
+    let res = Request::get(format!("http://localhost:{}", port))
+        .empty()
+        .unwrap()
+        .send_with_opts(opts);
+
+    match res {
+        Err(Error::Io(err)) => {
+            assert_eq!(ErrorKind::TimedOut, err.kind());
+            assert!(started.elapsed() < Duration::from_millis(500));
+        }
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(resp) => panic!("Unexpected response: {}", resp.status()),
+    }
+
+    server.join().unwrap();
+}
+
+#[test]
+fn first_byte_timeout_does_not_limit_body() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(move || {
+        let (mut stream, _peer_addr) = listener.accept().unwrap();
+
+        stream
+            .write_all(b"HTTP/1.1 200 Ok\r\nContent-Length: 6\r\n\r\nfoo")
+            .unwrap();
+        sleep(Duration::from_millis(300));
+        stream.write_all(b"bar").unwrap();
+    });
+
+    let mut opts = Options::default();
+    opts.first_byte_timeout = Some(Duration::from_millis(100));
+
+    let resp = Request::get(format!("http://localhost:{}", port))
+        .empty()
+        .unwrap()
+        .send_with_opts(opts)
+        .unwrap();
+
+    assert_eq!("foobar", resp.into_string().unwrap());
+
+    server.join().unwrap();
+}